@@ -23,6 +23,7 @@ fn write_moves(
 
 fn main() {
     write_magics();
+    write_cuckoo();
     write_network();
 }
 
@@ -60,6 +61,95 @@ fn write_magics() {
     writeln!(out_file, "];").unwrap();
 }
 
+/*
+Cuckoo tables of every reversible move (a non-pawn piece moving between two squares),
+indexed by the change in hash the move causes. The table is filled with cuckoo hashing,
+so every key can be found at one of `cuckoo_h1(key)` or `cuckoo_h2(key)`.
+*/
+fn write_cuckoo() {
+    let mut keys = [0u64; CUCKOO_TABLE_SIZE];
+    let mut moves = [0u16; CUCKOO_TABLE_SIZE];
+    let mut count = 0;
+
+    for &color in &Color::ALL {
+        for &piece in &[
+            Piece::Knight,
+            Piece::Bishop,
+            Piece::Rook,
+            Piece::Queen,
+            Piece::King,
+        ] {
+            for &s1 in &Square::ALL {
+                let attacks = match piece {
+                    Piece::Knight => knight_attacks(s1),
+                    Piece::Bishop => bishop_rays(s1),
+                    Piece::Rook => rook_rays(s1),
+                    Piece::Queen => queen_rays(s1),
+                    _ => king_attacks(s1),
+                };
+
+                for s2 in attacks {
+                    if s2 <= s1 {
+                        continue;
+                    }
+
+                    let mut key = ZOBRIST.piece(s1, piece, color)
+                        ^ ZOBRIST.piece(s2, piece, color)
+                        ^ ZOBRIST.stm();
+                    let mut mv = Move::new(s1, s2, MoveFlag::Normal).bits();
+                    let mut i = cuckoo_h1(key);
+
+                    loop {
+                        std::mem::swap(&mut keys[i], &mut key);
+                        std::mem::swap(&mut moves[i], &mut mv);
+
+                        if mv == 0 {
+                            break;
+                        }
+
+                        i = if i == cuckoo_h1(key) {
+                            cuckoo_h2(key)
+                        } else {
+                            cuckoo_h1(key)
+                        };
+                    }
+
+                    count += 1;
+                }
+            }
+        }
+    }
+
+    assert_eq!(count, 3668, "Unexpected number of reversible moves");
+
+    let mut out_file: PathBuf = env::var_os("OUT_DIR").unwrap().into();
+    out_file.push("cuckoo.rs");
+
+    let mut out_file = BufWriter::new(fs::File::create(out_file).unwrap());
+
+    writeln!(
+        out_file,
+        "const CUCKOO_KEYS: &[u64; {}] = &[",
+        CUCKOO_TABLE_SIZE
+    )
+    .unwrap();
+    for &key in keys.iter() {
+        writeln!(out_file, "{key:#X},").unwrap();
+    }
+    writeln!(out_file, "];").unwrap();
+
+    writeln!(
+        out_file,
+        "const CUCKOO_MOVES: &[u16; {}] = &[",
+        CUCKOO_TABLE_SIZE
+    )
+    .unwrap();
+    for &mv in moves.iter() {
+        writeln!(out_file, "{mv},").unwrap();
+    }
+    writeln!(out_file, "];").unwrap();
+}
+
 fn write_network() {
     let network_dir = env::var("EVALFILE").unwrap_or(String::from("./networks/default.nnue"));
     let network_path = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("network.nnue");
@@ -10,9 +10,12 @@ mod move_gen;
 mod parse;
 mod perft;
 mod print;
+mod san;
 mod startpos;
 
 pub use move_gen::*;
+pub use parse::*;
+pub use perft::*;
 
 /*----------------------------------------------------------------*/
 
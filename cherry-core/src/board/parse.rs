@@ -1,20 +1,101 @@
-use core::fmt::Write;
+use core::fmt::{self, Write};
 
 use crate::*;
 
+/*----------------------------------------------------------------*/
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FenError {
+    MissingField(&'static str),
+    TooManyFields,
+    InvalidRankCount(usize),
+    InvalidRank(usize),
+    InvalidPiece(char),
+    TooManyPieces(Color),
+    TooManyKings(Color),
+    MissingKing(Color),
+    InvalidSideToMove(String),
+    InvalidCastleRights(String),
+    ImpossibleCastleRights(char),
+    InvalidEnPassant(String),
+    ImpossibleEnPassant(Square),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveCount(String),
+    PawnOnBackRank(Square),
+    OpponentInCheck,
+    TooManyCheckers(usize),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let color_name = |color: &Color| match color {
+            Color::White => "White",
+            Color::Black => "Black",
+        };
+
+        match self {
+            FenError::MissingField(field) => write!(f, "Missing Field: {field}"),
+            FenError::TooManyFields => write!(f, "Too Many Fields"),
+            FenError::InvalidRankCount(count) => write!(f, "Expected 8 Ranks, Found {count}"),
+            FenError::InvalidRank(rank) => write!(f, "Rank {rank} Does Not Have 8 Files"),
+            FenError::InvalidPiece(piece) => write!(f, "Invalid Piece: `{piece}`"),
+            FenError::TooManyPieces(color) => write!(f, "Too Many {} Pieces", color_name(color)),
+            FenError::TooManyKings(color) => write!(f, "Too Many {} Kings", color_name(color)),
+            FenError::MissingKing(color) => write!(f, "Missing {} King", color_name(color)),
+            FenError::InvalidSideToMove(stm) => write!(f, "Invalid Side to Move: `{stm}`"),
+            FenError::InvalidCastleRights(rights) =>
+                write!(f, "Invalid Castling Rights: `{rights}`"),
+            FenError::ImpossibleCastleRights(right) =>
+                write!(f, "Impossible Castling Right: `{right}`"),
+            FenError::InvalidEnPassant(ep) => write!(f, "Invalid En Passant Square: `{ep}`"),
+            FenError::ImpossibleEnPassant(sq) => write!(f, "Impossible En Passant Square: `{sq}`"),
+            FenError::InvalidHalfmoveClock(clock) => write!(f, "Invalid Halfmove Clock: `{clock}`"),
+            FenError::InvalidFullmoveCount(count) => write!(f, "Invalid Fullmove Count: `{count}`"),
+            FenError::PawnOnBackRank(sq) => write!(f, "Pawn on Back Rank: `{sq}`"),
+            FenError::OpponentInCheck => write!(f, "Side Not to Move is in Check"),
+            FenError::TooManyCheckers(count) => write!(f, "Too Many Checkers: {count}"),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+/*----------------------------------------------------------------*/
+
 impl Board {
+    /*
+    Parses FEN, Shredder-FEN and X-FEN.
+    Castling rights can be given as `KQkq`, which refers to the outermost rook on either side,
+    or as the file of the castling rook, which is needed when there are multiple rooks on one side.
+    */
+    #[inline]
+    pub fn from_fen(fen: &str) -> Result<Board, FenError> {
+        Board::parse_fen(fen, false)
+    }
+
+    /*
+    Like `from_fen`, but also rejects positions that can't arise in a legal game:
+    pawns on the back ranks, the side not to move being in check, more than two checkers,
+    duplicate castling rights, out of range clocks or an en passant square
+    that a double pushed pawn could not have left behind.
+    */
     #[inline]
-    pub fn from_fen(fen: &str) -> Option<Board> {
+    pub fn from_fen_strict(fen: &str) -> Result<Board, FenError> {
+        Board::parse_fen(fen, true)
+    }
+
+    fn parse_fen(fen: &str, strict: bool) -> Result<Board, FenError> {
         let mut parts = fen.trim().split_ascii_whitespace();
-        let pieces = parts.next()?;
-        let stm = parts.next()?;
-        let castle_rights = parts.next()?;
-        let en_passant = parts.next()?;
-        let halfmove_clock = parts.next()?;
-        let fullmove_count = parts.next()?;
+        let mut next_part = |field| parts.next().ok_or(FenError::MissingField(field));
+        let pieces = next_part("piece placement")?;
+        let stm = next_part("side to move")?;
+        let castle_rights = next_part("castling rights")?;
+        let en_passant = next_part("en passant square")?;
+        let halfmove_clock = next_part("halfmove clock")?;
+        let fullmove_count = next_part("fullmove count")?;
 
         if parts.next().is_some() {
-            return None;
+            return Err(FenError::TooManyFields);
         }
 
         let mut board = Board {
@@ -37,21 +118,31 @@ impl Board {
             stm: Color::White,
         };
 
+        let rows = pieces.split('/').collect::<Vec<_>>();
+        if rows.len() != Rank::COUNT {
+            return Err(FenError::InvalidRankCount(rows.len()));
+        }
+
         let mut white_index = 0;
         let mut black_index = 0;
 
-        for (rank, row) in pieces.rsplit('/').enumerate() {
-            let rank = Rank::try_index(rank)?;
+        for (rank, row) in rows.into_iter().rev().enumerate() {
+            let invalid_rank = FenError::InvalidRank(rank + 1);
+            let rank = Rank::index(rank);
             let mut file = 0;
 
             for p in row.chars() {
                 if let Some(empty) = p.to_digit(10) {
                     file += empty as usize;
                 } else {
-                    let piece = p.try_into().ok()?;
+                    let piece = p.try_into().map_err(|_| FenError::InvalidPiece(p))?;
                     let color = Color::index(p.is_ascii_lowercase() as usize);
-                    let sq = Square::new(File::try_index(file)?, rank);
+                    let sq = Square::new(File::try_index(file).ok_or(invalid_rank.clone())?, rank);
                     let index = if piece == Piece::King {
+                        if board.index_to_piece[color][PieceIndex(0)].is_some() {
+                            return Err(FenError::TooManyKings(color));
+                        }
+
                         PieceIndex(0)
                     } else {
                         let index = match color {
@@ -61,7 +152,7 @@ impl Board {
 
                         *index += 1;
                         if *index >= PieceIndex::COUNT {
-                            return None;
+                            return Err(FenError::TooManyPieces(color));
                         }
 
                         PieceIndex(*index as u8)
@@ -76,24 +167,23 @@ impl Board {
             }
 
             if file != File::COUNT {
-                return None;
+                return Err(invalid_rank);
             }
         }
 
-        if board.index_to_piece[Color::White][PieceIndex(0)].is_none()
-            || board.index_to_piece[Color::Black][PieceIndex(0)].is_none()
-        {
-            return None;
-        }
-
-        if stm.len() != 1 {
-            return None;
+        for color in Color::ALL {
+            if board.index_to_piece[color][PieceIndex(0)].is_none() {
+                return Err(FenError::MissingKing(color));
+            }
         }
 
-        board.stm = stm.chars().next().unwrap().try_into().ok()?;
+        board.stm = stm
+            .parse::<Color>()
+            .map_err(|_| FenError::InvalidSideToMove(stm.to_string()))?;
 
-        if castle_rights.len() < 1 || castle_rights.len() > 4 {
-            return None;
+        let invalid_castle_rights = || FenError::InvalidCastleRights(castle_rights.to_string());
+        if castle_rights.is_empty() || castle_rights.len() > 4 {
+            return Err(invalid_castle_rights());
         }
 
         if castle_rights != "-" {
@@ -103,29 +193,44 @@ impl Board {
                 let our_king = board.king(color);
 
                 if our_king.rank() != our_backrank {
-                    return None;
+                    return Err(FenError::ImpossibleCastleRights(c));
                 }
 
+                let our_rooks = board.color_pieces(color, Piece::Rook) & our_backrank.bitboard();
                 let rook_file = match c.to_ascii_lowercase() {
-                    'a'..='h' => c.try_into().ok()?,
+                    'a'..='h' => {
+                        let file = File::try_from(c).unwrap();
+                        our_rooks
+                            .has(Square::new(file, our_backrank))
+                            .then_some(file)
+                    }
                     'k' => {
                         let corner_rook = Square::new(File::H, our_backrank);
                         let rook_mask = between(our_king, corner_rook) | corner_rook;
-                        let valid_rooks = board.color_pieces(color, Piece::Rook) & rook_mask;
 
-                        valid_rooks.try_next_square_back().map(Square::file)?
+                        (our_rooks & rook_mask)
+                            .try_next_square_back()
+                            .map(Square::file)
                     }
                     'q' => {
                         let corner_rook = Square::new(File::A, our_backrank);
                         let rook_mask = between(our_king, corner_rook) | corner_rook;
-                        let valid_rooks = board.color_pieces(color, Piece::Rook) & rook_mask;
 
-                        valid_rooks.try_next_square().map(Square::file)?
+                        (our_rooks & rook_mask).try_next_square().map(Square::file)
                     }
-                    _ => return None,
+                    _ => return Err(invalid_castle_rights()),
                 };
 
-                board.set_castle_rights(color, rook_file > our_king.file(), Some(rook_file));
+                let rook_file = rook_file.ok_or(FenError::ImpossibleCastleRights(c))?;
+                let short = rook_file > our_king.file();
+                let rights = board.castle_rights[color];
+                let prev = if short { rights.short } else { rights.long };
+
+                if strict && prev.is_some() {
+                    return Err(invalid_castle_rights());
+                }
+
+                board.set_castle_rights(color, short, Some(rook_file));
             }
         }
 
@@ -133,19 +238,64 @@ impl Board {
         board.calc_attacks();
         board.calc_pinned();
 
+        if strict {
+            let back_ranks = Rank::First.bitboard() | Rank::Eighth.bitboard();
+            if let Some(sq) = (board.pieces(Piece::Pawn) & back_ranks).try_next_square() {
+                return Err(FenError::PawnOnBackRank(sq));
+            }
+
+            if !board.attack_table[board.stm]
+                .get(board.king(!board.stm))
+                .is_empty()
+            {
+                return Err(FenError::OpponentInCheck);
+            }
+
+            let checkers = board.checkers().popcnt();
+            if checkers > 2 {
+                return Err(FenError::TooManyCheckers(checkers));
+            }
+        }
+
         if en_passant != "-" {
-            let ep_sq = en_passant.parse::<Square>().ok()?;
+            let ep_sq = en_passant
+                .parse::<Square>()
+                .map_err(|_| FenError::InvalidEnPassant(en_passant.to_string()))?;
             if ep_sq.rank() != Rank::Sixth.relative_to(board.stm) {
-                return None;
+                return Err(FenError::InvalidEnPassant(en_passant.to_string()));
+            }
+
+            if strict {
+                let sign = board.stm.sign() as i8;
+                let ep_victim = ep_sq.offset(0, -sign);
+                let ep_src = ep_sq.offset(0, sign);
+                let is_victim = board.piece_on(ep_victim) == Some(Piece::Pawn)
+                    && board.color_on(ep_victim) == Some(!board.stm);
+
+                if !is_victim || board.piece_on(ep_sq).is_some() || board.piece_on(ep_src).is_some()
+                {
+                    return Err(FenError::ImpossibleEnPassant(ep_sq));
+                }
             }
 
             board.calc_ep(Some(ep_sq));
         }
 
-        board.halfmove_clock = halfmove_clock.parse::<u8>().ok()?.min(100);
-        board.fullmove_count = fullmove_count.parse::<u16>().ok()?.max(1);
-
-        Some(board)
+        let halfmove_clock = halfmove_clock
+            .parse::<u8>()
+            .ok()
+            .filter(|&clock| !strict || clock <= 100)
+            .ok_or_else(|| FenError::InvalidHalfmoveClock(halfmove_clock.to_string()))?;
+        let fullmove_count = fullmove_count
+            .parse::<u16>()
+            .ok()
+            .filter(|&count| !strict || count > 0)
+            .ok_or_else(|| FenError::InvalidFullmoveCount(fullmove_count.to_string()))?;
+
+        board.halfmove_clock = halfmove_clock.min(100);
+        board.fullmove_count = fullmove_count.max(1);
+
+        Ok(board)
     }
 
     #[inline]
@@ -233,3 +383,134 @@ impl Board {
         fen
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fen_errors() {
+        for (fen, err) in [
+            (
+                "8/8/8/8/8/8/8/8 w - -",
+                FenError::MissingField("halfmove clock"),
+            ),
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1 0", FenError::TooManyFields),
+            ("4k3/8/8/8/8/8/4K3 w - - 0 1", FenError::InvalidRankCount(7)),
+            ("4k3/8/8/8/8/8/8/4K4 w - - 0 1", FenError::InvalidRank(1)),
+            ("4k3/8/8/8/8/8/8/4X3 w - - 0 1", FenError::InvalidPiece('X')),
+            (
+                "4k3/8/8/8/8/8/8/8 w - - 0 1",
+                FenError::MissingKing(Color::White),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/3KK3 w - - 0 1",
+                FenError::TooManyKings(Color::White),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 x - - 0 1",
+                FenError::InvalidSideToMove("x".to_string()),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w X - 0 1",
+                FenError::InvalidCastleRights("X".to_string()),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w K - 0 1",
+                FenError::ImpossibleCastleRights('K'),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/R3K3 w B - 0 1",
+                FenError::ImpossibleCastleRights('B'),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - e4 0 1",
+                FenError::InvalidEnPassant("e4".to_string()),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - x 1",
+                FenError::InvalidHalfmoveClock("x".to_string()),
+            ),
+        ] {
+            assert_eq!(Board::from_fen(fen).unwrap_err(), err, "{fen}");
+        }
+
+        for (fen, err) in [
+            (
+                "4k2P/8/8/8/8/8/8/4K3 w - - 0 1",
+                FenError::PawnOnBackRank(Square::H8),
+            ),
+            ("4k3/8/8/8/4R3/8/8/4K3 w - - 0 1", FenError::OpponentInCheck),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - e6 0 1",
+                FenError::ImpossibleEnPassant(Square::E6),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/R3K2R w KKQ - 0 1",
+                FenError::InvalidCastleRights("KKQ".to_string()),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - 101 1",
+                FenError::InvalidHalfmoveClock("101".to_string()),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - 0 0",
+                FenError::InvalidFullmoveCount("0".to_string()),
+            ),
+        ] {
+            assert!(Board::from_fen(fen).is_ok(), "{fen}");
+            assert_eq!(Board::from_fen_strict(fen).unwrap_err(), err, "{fen}");
+        }
+
+        assert!(
+            Board::from_fen_strict("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn shredder_and_x_fen() {
+        let expected = |white_short, white_long, black_short, black_long| {
+            (
+                CastleRights {
+                    short: white_short,
+                    long: white_long,
+                },
+                CastleRights {
+                    short: black_short,
+                    long: black_long,
+                },
+            )
+        };
+
+        for (fen, rights) in [
+            (
+                "rbbqn1kr/pp2p1pp/6n1/2pp1p2/2P4P/P7/BP1PPPP1/R1BQNNKR w HAha - 0 9",
+                expected(Some(File::H), Some(File::A), Some(File::H), Some(File::A)),
+            ),
+            (
+                "rbbqn1kr/pp2p1pp/6n1/2pp1p2/2P4P/P7/BP1PPPP1/R1BQNNKR w KQkq - 0 9",
+                expected(Some(File::H), Some(File::A), Some(File::H), Some(File::A)),
+            ),
+            (
+                "1r2k1r1/8/8/8/8/8/8/RR2K2R w KBg - 0 1",
+                expected(Some(File::H), Some(File::B), Some(File::G), None),
+            ),
+            (
+                "1r2k1r1/8/8/8/8/8/8/RR2K2R w Qkq - 0 1",
+                expected(None, Some(File::A), Some(File::G), Some(File::B)),
+            ),
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+
+            assert_eq!(
+                (
+                    board.castle_rights(Color::White),
+                    board.castle_rights(Color::Black)
+                ),
+                rights,
+                "{fen}"
+            );
+        }
+    }
+}
@@ -1,3 +1,185 @@
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use crate::*;
+
+/*----------------------------------------------------------------*/
+
+pub const DEFAULT_PERFT_HASH: usize = 64;
+
+/*
+Lockless perft hash table.
+Each entry stores the node count and depth in `data`, and the hash XORed with `data` in `key`,
+so that an entry torn by a concurrent write fails verification instead of returning a wrong count.
+*/
+#[derive(Debug)]
+pub struct PerftTable {
+    entries: Box<[PerftSlot]>,
+}
+
+#[derive(Debug, Default)]
+struct PerftSlot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl PerftTable {
+    #[inline]
+    pub fn new(mb: usize) -> PerftTable {
+        let size = (mb * 1024 * 1024 / size_of::<PerftSlot>()).max(1);
+
+        PerftTable {
+            entries: (0..size).map(|_| PerftSlot::default()).collect(),
+        }
+    }
+
+    #[inline]
+    fn probe(&self, hash: u64, depth: u8) -> Option<u64> {
+        let slot = &self.entries[self.index(hash)];
+        let data = slot.data.load(Ordering::Relaxed);
+        let key = slot.key.load(Ordering::Relaxed);
+
+        (key ^ data == hash && data as u8 == depth).then_some(data >> 8)
+    }
+
+    #[inline]
+    fn store(&self, hash: u64, depth: u8, nodes: u64) {
+        let slot = &self.entries[self.index(hash)];
+        let data = nodes << 8 | depth as u64;
+
+        slot.data.store(data, Ordering::Relaxed);
+        slot.key.store(hash ^ data, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn index(&self, hash: u64) -> usize {
+        ((u128::from(hash) * self.entries.len() as u128) >> 64) as usize
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+impl Board {
+    pub fn perft(&self, depth: u8, bulk: bool) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let move_list = self.gen_moves();
+        if bulk && depth == 1 {
+            return move_list.len() as u64;
+        }
+
+        let mut nodes = 0;
+        for &mv in move_list.iter() {
+            let mut board = self.clone();
+            board.make_move(mv);
+
+            nodes += board.perft(depth - 1, bulk);
+        }
+
+        nodes
+    }
+
+    pub fn hashed_perft(&self, depth: u8, bulk: bool, table: &PerftTable) -> u64 {
+        if depth <= 1 {
+            return self.perft(depth, bulk);
+        }
+
+        if let Some(nodes) = table.probe(self.hash(), depth) {
+            return nodes;
+        }
+
+        let mut nodes = 0;
+        for &mv in self.gen_moves().iter() {
+            let mut board = self.clone();
+            board.make_move(mv);
+
+            nodes += board.hashed_perft(depth - 1, bulk, table);
+        }
+
+        table.store(self.hash(), depth, nodes);
+        nodes
+    }
+
+    /*
+    Counts the nodes below each root move, handing out root moves to `threads` threads
+    as they become free. All threads share the same perft hash table.
+    */
+    pub fn split_perft(
+        &self,
+        depth: u8,
+        bulk: bool,
+        threads: usize,
+        table: &PerftTable,
+    ) -> Vec<(Move, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+
+        let moves = self.gen_moves();
+        let counts = (0..moves.len())
+            .map(|_| AtomicU64::new(0))
+            .collect::<Vec<_>>();
+        let next = AtomicUsize::new(0);
+
+        std::thread::scope(|s| {
+            for _ in 0..threads.clamp(1, moves.len().max(1)) {
+                s.spawn(|| {
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(&mv) = moves.get(i) else {
+                            break;
+                        };
+
+                        let mut board = self.clone();
+                        board.make_move(mv);
+
+                        let nodes = board.hashed_perft(depth - 1, bulk, table);
+                        counts[i].store(nodes, Ordering::Relaxed);
+                    }
+                });
+            }
+        });
+
+        moves
+            .iter()
+            .zip(counts)
+            .map(|(&mv, nodes)| (mv, nodes.into_inner()))
+            .collect()
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+/*
+A line of a perft EPD suite, e.g.
+`rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D2 400 ;D3 8902`
+*/
+#[derive(Debug, Clone)]
+pub struct PerftSuiteEntry {
+    pub board: Board,
+    pub counts: Vec<(u8, u64)>,
+}
+
+impl PerftSuiteEntry {
+    pub fn parse(line: &str) -> Option<PerftSuiteEntry> {
+        let mut fields = line.split(';');
+        let board = Board::from_fen(fields.next()?).ok()?;
+        let counts = fields
+            .map(|field| {
+                let (depth, nodes) = field.trim().split_once(' ')?;
+                let depth = depth.strip_prefix('D')?.parse::<u8>().ok()?;
+
+                Some((depth, nodes.trim().parse::<u64>().ok()?))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(PerftSuiteEntry { board, counts })
+    }
+}
+
+/*----------------------------------------------------------------*/
+
 #[cfg(test)]
 mod tests {
     use crate::*;
@@ -130,6 +312,26 @@ mod tests {
         };
     }
 
+    #[test]
+    fn perft_suite() {
+        let table = PerftTable::new(16);
+
+        for line in include_str!("../../perftsuite.epd").lines() {
+            let entry = PerftSuiteEntry::parse(line).unwrap();
+
+            for &(depth, nodes) in &entry.counts {
+                let perft_nodes = entry
+                    .board
+                    .split_perft(depth, true, 4, &table)
+                    .iter()
+                    .map(|&(_, nodes)| nodes)
+                    .sum::<u64>();
+
+                assert_eq!(perft_nodes, nodes, "{line} | Depth: {depth}");
+            }
+        }
+    }
+
     perft_test!(
         perft_startpos: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         1,
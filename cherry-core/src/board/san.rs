@@ -0,0 +1,210 @@
+use crate::*;
+
+/*----------------------------------------------------------------*/
+
+impl Move {
+    pub fn to_san(self, board: &Board) -> String {
+        let mut san = String::new();
+
+        match self.flag() {
+            MoveFlag::ShortCastling => san.push_str("O-O"),
+            MoveFlag::LongCastling => san.push_str("O-O-O"),
+            _ => {
+                let (src, dest) = (self.src(), self.dest());
+                let piece = board.piece_on(src).unwrap();
+
+                if piece == Piece::Pawn {
+                    if self.is_capture() {
+                        san.push(char::from(src.file()));
+                    }
+                } else {
+                    san.push(char::from(piece).to_ascii_uppercase());
+
+                    let others = board
+                        .gen_moves()
+                        .iter()
+                        .filter(|&&mv| {
+                            mv != self
+                                && mv.dest() == dest
+                                && !mv.is_castling()
+                                && board.piece_on(mv.src()) == Some(piece)
+                        })
+                        .map(|mv| mv.src())
+                        .collect::<Vec<_>>();
+
+                    if !others.is_empty() {
+                        if others.iter().all(|sq| sq.file() != src.file()) {
+                            san.push(char::from(src.file()));
+                        } else if others.iter().all(|sq| sq.rank() != src.rank()) {
+                            san.push(char::from(src.rank()));
+                        } else {
+                            san.push_str(&src.to_string());
+                        }
+                    }
+                }
+
+                if self.is_capture() {
+                    san.push('x');
+                }
+
+                san.push_str(&dest.to_string());
+
+                if let Some(promotion) = self.promotion() {
+                    san.push('=');
+                    san.push(char::from(promotion).to_ascii_uppercase());
+                }
+            }
+        }
+
+        let mut board = board.clone();
+        board.make_move(self);
+
+        match board.status() {
+            BoardStatus::Checkmate => san.push('#'),
+            _ if board.in_check() => san.push('+'),
+            _ => {}
+        }
+
+        san
+    }
+
+    pub fn parse_san(board: &Board, san: &str) -> Option<Move> {
+        let san = san.trim_end_matches(['+', '#', '!', '?']);
+        let moves = board.gen_moves();
+
+        let castling = match san {
+            "O-O" | "0-0" => Some(MoveFlag::ShortCastling),
+            "O-O-O" | "0-0-0" => Some(MoveFlag::LongCastling),
+            _ => None,
+        };
+
+        if let Some(flag) = castling {
+            return moves.iter().copied().find(|mv| mv.flag() == flag);
+        }
+
+        let (piece, san) = match san.chars().next()? {
+            c @ ('N' | 'B' | 'R' | 'Q' | 'K') => (Piece::try_from(c).ok()?, &san[1..]),
+            _ => (Piece::Pawn, san),
+        };
+
+        let (san, promotion) = match san.char_indices().last()? {
+            (i, c @ ('N' | 'B' | 'R' | 'Q')) => (
+                san[..i].trim_end_matches('='),
+                Some(Piece::try_from(c).ok()?),
+            ),
+            _ => (san, None),
+        };
+
+        let dest = san
+            .get(san.len().checked_sub(2)?..)?
+            .parse::<Square>()
+            .ok()?;
+        let mut src_file = None;
+        let mut src_rank = None;
+
+        for c in san[..san.len() - 2].chars() {
+            match c {
+                'a'..='h' => src_file = Some(File::try_from(c).ok()?),
+                '1'..='8' => src_rank = Some(Rank::try_from(c).ok()?),
+                'x' | '-' | ':' => {}
+                _ => return None,
+            }
+        }
+
+        let mut candidates = moves.iter().copied().filter(|&mv| {
+            mv.dest() == dest
+                && !mv.is_castling()
+                && mv.promotion() == promotion
+                && board.piece_on(mv.src()) == Some(piece)
+                && src_file.is_none_or(|f| mv.src().file() == f)
+                && src_rank.is_none_or(|r| mv.src().rank() == r)
+        });
+
+        let mv = candidates.next()?;
+        if candidates.next().is_some() {
+            return None;
+        }
+
+        Some(mv)
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn round_trip(board: &Board, depth: u8) {
+        for &mv in board.gen_moves().iter() {
+            let san = mv.to_san(board);
+
+            assert_eq!(
+                Move::parse_san(board, &san),
+                Some(mv),
+                "{} | {}",
+                board.to_fen(true),
+                san
+            );
+
+            if depth > 1 {
+                let mut board = board.clone();
+                board.make_move(mv);
+                round_trip(&board, depth - 1);
+            }
+        }
+    }
+
+    #[test]
+    fn san_round_trip() {
+        const FENS: &[&str] = &[
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            "1rqbkrbn/1ppppp1p/1n6/p1N3p1/8/2P4P/PP1PPPP1/1RQBKRBN w FBfb - 0 9",
+            "rbbqn1kr/pp2p1pp/6n1/2pp1p2/2P4P/P7/BP1PPPP1/R1BQNNKR w HAha - 0 9",
+        ];
+
+        for fen in FENS {
+            round_trip(&Board::from_fen(fen).unwrap(), 3);
+        }
+    }
+
+    #[test]
+    fn san_notation() {
+        let board =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+
+        for (uci, san) in [
+            ("e1h1", "O-O"),
+            ("e1a1", "O-O-O"),
+            ("d5e6", "dxe6"),
+            ("e5f7", "Nxf7"),
+            ("e2a6", "Bxa6"),
+            ("f3f6", "Qxf6"),
+        ] {
+            let mv = Move::parse(&board, uci).unwrap();
+
+            assert_eq!(mv.to_san(&board), san);
+            assert_eq!(Move::parse_san(&board, san), Some(mv));
+        }
+
+        for (fen, uci, san) in [
+            ("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8", "Ra8#"),
+            ("4k3/8/8/8/8/8/4K3/R6R w - - 0 1", "a1d1", "Rad1"),
+            ("4k3/R7/8/8/8/8/8/R3K3 w - - 0 1", "a1a4", "R1a4"),
+            ("4k3/8/8/8/8/8/1Q1Q4/1Q2K3 w - - 0 1", "b2c1", "Qb2c1"),
+            ("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8n", "b8=N"),
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            let mv = Move::parse(&board, uci).unwrap();
+
+            assert_eq!(mv.to_san(&board), san);
+            assert_eq!(Move::parse_san(&board, san), Some(mv));
+        }
+    }
+}
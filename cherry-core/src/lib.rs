@@ -9,6 +9,7 @@ mod dir;
 mod file;
 mod geometry;
 mod magic;
+mod pgn;
 mod piece;
 mod rank;
 mod simd;
@@ -24,6 +25,7 @@ pub use dir::*;
 pub use file::*;
 pub use geometry::*;
 pub use magic::*;
+pub use pgn::*;
 pub use piece::*;
 pub use rank::*;
 pub use simd::*;
@@ -0,0 +1,743 @@
+use core::fmt::{self, Write};
+
+use crate::*;
+
+/*----------------------------------------------------------------*/
+
+const STARTPOS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const MAX_LINE_LEN: usize = 80;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GameResult {
+    WhiteWin,
+    BlackWin,
+    Draw,
+    Unknown,
+}
+
+impl GameResult {
+    #[inline]
+    pub fn parse(s: &str) -> Option<GameResult> {
+        match s {
+            "1-0" => Some(GameResult::WhiteWin),
+            "0-1" => Some(GameResult::BlackWin),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Unknown),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameResult::WhiteWin => write!(f, "1-0"),
+            GameResult::BlackWin => write!(f, "0-1"),
+            GameResult::Draw => write!(f, "1/2-1/2"),
+            GameResult::Unknown => write!(f, "*"),
+        }
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PgnError {
+    InvalidTag(String),
+    InvalidFen(String, FenError),
+    IllegalMove(String),
+    UnexpectedToken(String),
+    UnterminatedComment,
+    UnterminatedVariation,
+    UnexpectedEnd,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::InvalidTag(tag) => write!(f, "Invalid Tag: `{tag}`"),
+            PgnError::InvalidFen(fen, err) => write!(f, "Invalid FEN: `{fen}` ({err})"),
+            PgnError::IllegalMove(mv) => write!(f, "Illegal Move: `{mv}`"),
+            PgnError::UnexpectedToken(token) => write!(f, "Unexpected Token: `{token}`"),
+            PgnError::UnterminatedComment => write!(f, "Unterminated Comment"),
+            PgnError::UnterminatedVariation => write!(f, "Unterminated Variation"),
+            PgnError::UnexpectedEnd => write!(f, "Unexpected End of Game"),
+        }
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnMove {
+    pub mv: Move,
+    //Comments written before the move, like at the start of a variation
+    pub pre_comments: Vec<String>,
+    pub nags: Vec<u8>,
+    pub comments: Vec<String>,
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    #[inline]
+    pub fn new(mv: Move) -> PgnMove {
+        PgnMove {
+            mv,
+            pre_comments: Vec::new(),
+            nags: Vec::new(),
+            comments: Vec::new(),
+            variations: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub start: Board,
+    pub comments: Vec<String>,
+    pub moves: Vec<PgnMove>,
+    pub result: GameResult,
+    pub frc: bool,
+}
+
+impl PgnGame {
+    pub fn new(start: Board, frc: bool) -> PgnGame {
+        let mut game = PgnGame {
+            tags: ["Event", "Site", "Date", "Round", "White", "Black", "Result"]
+                .iter()
+                .map(|&name| (String::from(name), String::from("?")))
+                .collect(),
+            start,
+            comments: Vec::new(),
+            moves: Vec::new(),
+            result: GameResult::Unknown,
+            frc,
+        };
+
+        game.set_tag("Result", "*");
+        if frc {
+            game.set_tag("Variant", "Chess960");
+        }
+
+        let fen = game.start.to_fen(frc);
+        if frc || fen != STARTPOS {
+            game.set_tag("SetUp", "1");
+            game.set_tag("FEN", &fen);
+        }
+
+        game
+    }
+
+    #[inline]
+    pub fn parse(pgn: &str) -> Result<PgnGame, PgnError> {
+        PgnReader::new(pgn).next().ok_or(PgnError::UnexpectedEnd)?
+    }
+
+    /*----------------------------------------------------------------*/
+
+    #[inline]
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = String::from(value),
+            None => self.tags.push((String::from(name), String::from(value))),
+        }
+    }
+
+    #[inline]
+    pub fn push(&mut self, mv: Move) {
+        self.moves.push(PgnMove::new(mv));
+    }
+
+    #[inline]
+    pub fn set_result(&mut self, result: GameResult) {
+        self.result = result;
+        self.set_tag("Result", &result.to_string());
+    }
+
+    /*----------------------------------------------------------------*/
+
+    //The position before each move of the mainline, together with the move played
+    pub fn positions(&self) -> Vec<(Board, Move)> {
+        let mut board = self.start.clone();
+        let mut positions = Vec::with_capacity(self.moves.len());
+
+        for mv in &self.moves {
+            positions.push((board.clone(), mv.mv));
+            board.make_move(mv.mv);
+        }
+
+        positions
+    }
+
+    pub fn end(&self) -> Board {
+        let mut board = self.start.clone();
+        for mv in &self.moves {
+            board.make_move(mv.mv);
+        }
+
+        board
+    }
+
+    /*----------------------------------------------------------------*/
+
+    pub fn to_pgn(&self) -> String {
+        let mut output = String::new();
+
+        for (name, value) in &self.tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(output, "[{name} \"{value}\"]").unwrap();
+        }
+
+        output.push('\n');
+
+        let mut tokens = Vec::new();
+        for comment in &self.comments {
+            tokens.push(format!("{{{comment}}}"));
+        }
+
+        write_line(&mut tokens, &self.start, &self.moves);
+        tokens.push(self.result.to_string());
+
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len != 0 && line_len + token.len() + 1 > MAX_LINE_LEN {
+                output.push('\n');
+                line_len = 0;
+            } else if line_len != 0 {
+                output.push(' ');
+                line_len += 1;
+            }
+
+            line_len += token.len();
+            output.push_str(&token);
+        }
+
+        output.push('\n');
+        output
+    }
+}
+
+impl fmt::Display for PgnGame {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_pgn())
+    }
+}
+
+fn write_line(tokens: &mut Vec<String>, board: &Board, moves: &[PgnMove]) {
+    let mut board = board.clone();
+    let mut needs_number = true;
+
+    for mv in moves {
+        let stm = board.stm();
+        let number = board.fullmove_count();
+
+        tokens.extend(
+            mv.pre_comments
+                .iter()
+                .map(|comment| format!("{{{comment}}}")),
+        );
+        needs_number |= !mv.pre_comments.is_empty();
+
+        if stm == Color::White {
+            tokens.push(format!("{number}."));
+        } else if needs_number {
+            tokens.push(format!("{number}..."));
+        }
+
+        tokens.push(mv.mv.to_san(&board));
+        tokens.extend(mv.nags.iter().map(|nag| format!("${nag}")));
+        tokens.extend(mv.comments.iter().map(|comment| format!("{{{comment}}}")));
+
+        needs_number = !mv.comments.is_empty() || !mv.variations.is_empty();
+        for variation in &mv.variations {
+            let mut line = Vec::new();
+            write_line(&mut line, &board, variation);
+
+            if let Some(first) = line.first_mut() {
+                first.insert(0, '(');
+            }
+            if let Some(last) = line.last_mut() {
+                last.push(')');
+            }
+
+            tokens.extend(line);
+        }
+
+        board.make_move(mv.mv);
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token<'a> {
+    Tag(&'a str, String),
+    Comment(&'a str),
+    Nag(u8),
+    VariationStart,
+    VariationEnd,
+    Result(GameResult),
+    San(&'a str),
+}
+
+pub struct PgnReader<'a> {
+    input: &'a str,
+    pos: usize,
+    peeked: Option<Result<Token<'a>, PgnError>>,
+}
+
+impl<'a> PgnReader<'a> {
+    #[inline]
+    pub fn new(input: &'a str) -> PgnReader<'a> {
+        PgnReader {
+            input,
+            pos: 0,
+            peeked: None,
+        }
+    }
+
+    fn peek(&mut self) -> Option<&Result<Token<'a>, PgnError>> {
+        if self.peeked.is_none() {
+            self.peeked = self.lex();
+        }
+
+        self.peeked.as_ref()
+    }
+
+    fn next_token(&mut self) -> Option<Result<Token<'a>, PgnError>> {
+        self.peeked.take().or_else(|| self.lex())
+    }
+
+    fn lex(&mut self) -> Option<Result<Token<'a>, PgnError>> {
+        loop {
+            let rest = &self.input[self.pos..];
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+
+            let at_line_start = self.input[..self.pos]
+                .chars()
+                .rev()
+                .find(|c| *c != ' ' && *c != '\t')
+                .is_none_or(|c| c == '\n');
+
+            match trimmed.chars().next()? {
+                '%' if at_line_start => self.skip_line(),
+                ';' => self.skip_line(),
+                //Move numbers are implied by the position, so they are skipped
+                c if c.is_ascii_digit() && self.skip_move_number() => {}
+                _ => return Some(self.lex_token()),
+            }
+        }
+    }
+
+    fn skip_line(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.find('\n').map_or(rest.len(), |i| i + 1);
+    }
+
+    fn skip_move_number(&mut self) -> bool {
+        let rest = &self.input[self.pos..];
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+
+        if rest[digits..].starts_with('.') {
+            let dots = rest[digits..]
+                .find(|c: char| c != '.')
+                .unwrap_or(rest.len() - digits);
+            self.pos += digits + dots;
+            return true;
+        }
+
+        false
+    }
+
+    fn lex_token(&mut self) -> Result<Token<'a>, PgnError> {
+        let rest = &self.input[self.pos..];
+        let c = rest.chars().next().unwrap();
+
+        match c {
+            '[' => {
+                let Some(end) = find_tag_end(rest) else {
+                    //skip the rest of the line, so that the next call makes progress
+                    let line_end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+                    self.pos += line_end;
+
+                    return Err(PgnError::InvalidTag(rest[..line_end].trim().to_string()));
+                };
+                self.pos += end + 1;
+
+                let inner = rest[1..end].trim();
+                let (name, value) = inner
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| PgnError::InvalidTag(inner.to_string()))?;
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .ok_or_else(|| PgnError::InvalidTag(inner.to_string()))?;
+
+                let mut unescaped = String::with_capacity(value.len());
+                let mut chars = value.chars();
+                while let Some(c) = chars.next() {
+                    if c == '\\'
+                        && let Some(next) = chars.next()
+                    {
+                        unescaped.push(next);
+                    } else {
+                        unescaped.push(c);
+                    }
+                }
+
+                Ok(Token::Tag(name, unescaped))
+            }
+            '{' => {
+                let Some(end) = rest.find('}') else {
+                    self.pos = self.input.len();
+                    return Err(PgnError::UnterminatedComment);
+                };
+                self.pos += end + 1;
+
+                Ok(Token::Comment(rest[1..end].trim()))
+            }
+            '(' => {
+                self.pos += 1;
+                Ok(Token::VariationStart)
+            }
+            ')' => {
+                self.pos += 1;
+                Ok(Token::VariationEnd)
+            }
+            '$' => {
+                let len = rest[1..]
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len() - 1);
+                self.pos += len + 1;
+
+                rest[1..len + 1]
+                    .parse::<u8>()
+                    .map(Token::Nag)
+                    .map_err(|_| PgnError::UnexpectedToken(rest[..len + 1].to_string()))
+            }
+            _ => {
+                let len = rest
+                    .find(|c: char| c.is_whitespace() || "{}()[];$".contains(c))
+                    .unwrap_or(rest.len());
+                self.pos += len.max(c.len_utf8());
+
+                let token = &rest[..len.max(c.len_utf8())];
+                if let Some(result) = GameResult::parse(token) {
+                    return Ok(Token::Result(result));
+                }
+
+                Ok(Token::San(token))
+            }
+        }
+    }
+
+    fn parse_line(&mut self, board: &Board, depth: usize) -> Result<Vec<PgnMove>, PgnError> {
+        let mut board = board.clone();
+        let mut prev_board: Option<Board> = None;
+        let mut moves: Vec<PgnMove> = Vec::new();
+        let mut pre_comments = Vec::new();
+
+        loop {
+            match self.peek() {
+                None if depth == 0 => return Ok(moves),
+                None => return Err(PgnError::UnterminatedVariation),
+                Some(Ok(Token::Result(_) | Token::Tag(..))) if depth == 0 => return Ok(moves),
+                Some(Ok(Token::Result(_) | Token::Tag(..))) => {
+                    return Err(PgnError::UnterminatedVariation);
+                }
+                _ => {}
+            }
+
+            match self.next_token().unwrap()? {
+                Token::Comment(comment) => match moves.last_mut() {
+                    Some(mv) => mv.comments.push(comment.to_string()),
+                    None => pre_comments.push(comment.to_string()),
+                },
+                Token::Nag(nag) => match moves.last_mut() {
+                    Some(mv) => mv.nags.push(nag),
+                    None => return Err(PgnError::UnexpectedToken(format!("${nag}"))),
+                },
+                Token::VariationStart => {
+                    let (Some(mv), Some(prev_board)) = (moves.last_mut(), &prev_board) else {
+                        return Err(PgnError::UnexpectedToken(String::from("(")));
+                    };
+
+                    let variation = self.parse_line(prev_board, depth + 1)?;
+                    mv.variations.push(variation);
+                }
+                Token::VariationEnd if depth > 0 => return Ok(moves),
+                Token::VariationEnd => return Err(PgnError::UnexpectedToken(String::from(")"))),
+                Token::San(san) => {
+                    let (san, nag) = split_suffix(san);
+                    let mv = Move::parse_san(&board, san)
+                        .or_else(|| Move::parse(&board, san).filter(|&mv| board.is_legal(mv)))
+                        .ok_or_else(|| PgnError::IllegalMove(san.to_string()))?;
+
+                    let mut pgn_move = PgnMove::new(mv);
+                    pgn_move.pre_comments = core::mem::take(&mut pre_comments);
+                    pgn_move.nags.extend(nag);
+                    moves.push(pgn_move);
+
+                    prev_board = Some(board.clone());
+                    board.make_move(mv);
+                }
+                Token::Result(_) | Token::Tag(..) => unreachable!(),
+            }
+        }
+    }
+}
+
+impl Iterator for PgnReader<'_> {
+    type Item = Result<PgnGame, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut tags = Vec::new();
+        while let Some(Ok(Token::Tag(..))) = self.peek() {
+            if let Some(Ok(Token::Tag(name, value))) = self.next_token() {
+                tags.push((name.to_string(), value));
+            }
+        }
+
+        if tags.is_empty() && self.peek().is_none() {
+            return None;
+        }
+
+        let game = self.parse_game(tags);
+        if game.is_err() {
+            self.skip_game();
+        }
+
+        Some(game)
+    }
+}
+
+impl PgnReader<'_> {
+    //Skips the rest of a broken game, up to its result or the tags of the next game
+    fn skip_game(&mut self) {
+        loop {
+            match self.peek() {
+                None | Some(Ok(Token::Tag(..))) => return,
+                Some(Ok(Token::Result(_))) => {
+                    self.next_token();
+                    return;
+                }
+                _ => {
+                    self.next_token();
+                }
+            }
+        }
+    }
+
+    fn parse_game(&mut self, tags: Vec<(String, String)>) -> Result<PgnGame, PgnError> {
+        let find = |name: &str| {
+            tags.iter()
+                .find(|(tag, _)| tag.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+
+        let frc = find("Variant").is_some_and(|variant| {
+            let variant = variant.to_ascii_lowercase();
+            variant.contains("960") || variant.contains("fischer")
+        });
+
+        let start = match find("FEN") {
+            Some(fen) =>
+                Board::from_fen(fen).map_err(|e| PgnError::InvalidFen(fen.to_string(), e))?,
+            None => Board::startpos(),
+        };
+
+        let mut comments = Vec::new();
+        while let Some(Ok(Token::Comment(_))) = self.peek() {
+            if let Some(Ok(Token::Comment(comment))) = self.next_token() {
+                comments.push(comment.to_string());
+            }
+        }
+
+        let moves = self.parse_line(&start, 0)?;
+        let result = match self.peek() {
+            Some(Ok(Token::Result(result))) => {
+                let result = *result;
+                self.next_token();
+                result
+            }
+            _ => find("Result")
+                .and_then(GameResult::parse)
+                .unwrap_or(GameResult::Unknown),
+        };
+
+        Ok(PgnGame {
+            tags,
+            start,
+            comments,
+            moves,
+            result,
+            frc,
+        })
+    }
+}
+
+fn find_tag_end(s: &str) -> Option<usize> {
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ']' if !in_string => return Some(i),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn split_suffix(san: &str) -> (&str, Option<u8>) {
+    let trimmed = san.trim_end_matches(['!', '?']);
+    let nag = match &san[trimmed.len()..] {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    };
+
+    (trimmed, nag)
+}
+
+/*----------------------------------------------------------------*/
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    const GAME: &str = r#"[Event "Test \"Quoted\""]
+[Site "?"]
+[Result "1-0"]
+
+{Opening comment} 1. e4 e5 2. Nf3 $1 {Develops} (2. f4 exf4 (2... d5) 3. Nf3) 2... Nc6
+3. Bb5!? a6 ; rest of line comment
+4. Ba4 Nf6 5. O-O 1-0
+
+[Event "Second"]
+[Variant "Chess960"]
+[SetUp "1"]
+[FEN "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1"]
+
+1. O-O O-O-O *
+"#;
+
+    #[test]
+    fn pgn_parse() {
+        let games = PgnReader::new(GAME).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(games.len(), 2);
+
+        let game = &games[0];
+        assert_eq!(game.tag("Event"), Some("Test \"Quoted\""));
+        assert_eq!(game.result, GameResult::WhiteWin);
+        assert_eq!(game.comments, vec![String::from("Opening comment")]);
+        assert_eq!(game.moves.len(), 9);
+        assert_eq!(game.moves[2].nags, vec![1]);
+        assert_eq!(game.moves[2].comments, vec![String::from("Develops")]);
+        assert_eq!(game.moves[2].variations.len(), 1);
+        assert_eq!(game.moves[2].variations[0][1].variations.len(), 1);
+        assert_eq!(game.moves[4].nags, vec![5]);
+        assert_eq!(game.moves[8].mv.flag(), MoveFlag::ShortCastling);
+
+        let game = &games[1];
+        assert!(game.frc);
+        assert_eq!(game.result, GameResult::Unknown);
+        assert_eq!(game.moves[0].mv.flag(), MoveFlag::ShortCastling);
+        assert_eq!(game.moves[1].mv.flag(), MoveFlag::LongCastling);
+        assert_eq!(
+            game.end().to_fen(true),
+            "2kr2r1/pppppppp/8/8/8/8/PPPPPPPP/1R3RK1 w - - 2 2"
+        );
+    }
+
+    #[test]
+    fn pgn_variation_comment() {
+        let game = PgnGame::parse("1. e4 ( {idea} 1. d4 ) e5 *").unwrap();
+        assert_eq!(game.moves.len(), 2);
+        assert_eq!(
+            game.moves[0].variations[0][0].pre_comments,
+            vec![String::from("idea")]
+        );
+
+        let game = PgnGame::parse("1. e4 e5 ( {idea} 1... c5 2. Nf3 ) 2. Nf3 *").unwrap();
+        let variation = &game.moves[1].variations[0];
+        assert_eq!(variation[0].pre_comments, vec![String::from("idea")]);
+        assert!(variation[0].comments.is_empty());
+
+        let written = game.to_pgn();
+        assert!(written.contains("({idea} 1... c5 2. Nf3)"));
+        assert_eq!(PgnGame::parse(&written).unwrap().moves, game.moves);
+    }
+
+    #[test]
+    fn pgn_truncated_input() {
+        assert_eq!(PgnReader::new("[Event \"x\"]\n1. e4 {oops").count(), 1);
+        assert_eq!(PgnReader::new("[Event \"x\"\n1. e4 e5 *").count(), 1);
+    }
+
+    #[test]
+    fn pgn_error_recovery() {
+        const GAMES: &str = r#"[Event "Illegal"]
+
+1. e4 e5 2. Ke3 Nc6 3. Bc4 (3. d4) Nf6 1-0
+
+[Event "Bad FEN"]
+[FEN "not a fen"]
+
+1. e4 e5 2. Nf3 *
+
+[Event "Valid"]
+
+1. d4 d5 2. c4 1/2-1/2
+"#;
+
+        let games = PgnReader::new(GAMES).collect::<Vec<_>>();
+        assert_eq!(games.len(), 3);
+        assert!(matches!(games[0], Err(PgnError::IllegalMove(_))));
+        assert!(matches!(games[1], Err(PgnError::InvalidFen(..))));
+
+        let game = games[2].as_ref().unwrap();
+        let board = Board::startpos();
+        assert_eq!(game.tag("Event"), Some("Valid"));
+        assert_eq!(game.moves.len(), 3);
+        assert_eq!(Some(game.moves[0].mv), Move::parse_san(&board, "d4"));
+        assert_eq!(game.result, GameResult::Draw);
+    }
+
+    #[test]
+    fn pgn_round_trip() {
+        for game in PgnReader::new(GAME) {
+            let game = game.unwrap();
+            let written = game.to_pgn();
+            let reparsed = PgnGame::parse(&written).unwrap();
+
+            assert_eq!(reparsed.tags, game.tags);
+            assert_eq!(reparsed.comments, game.comments);
+            assert_eq!(reparsed.moves, game.moves);
+            assert_eq!(reparsed.result, game.result);
+            assert_eq!(reparsed.to_pgn(), written);
+        }
+    }
+}
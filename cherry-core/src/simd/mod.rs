@@ -2,8 +2,12 @@
 mod avx2;
 #[cfg(target_feature = "avx512f")]
 mod avx512;
+#[cfg(not(any(target_feature = "avx2", target_feature = "avx512f")))]
+mod scalar;
 
 #[cfg(all(target_feature = "avx2", not(target_feature = "avx512f")))]
 pub use avx2::*;
 #[cfg(target_feature = "avx512f")]
 pub use avx512::*;
+#[cfg(not(any(target_feature = "avx2", target_feature = "avx512f")))]
+pub use scalar::*;
@@ -0,0 +1,817 @@
+use core::{ops::*, ptr};
+
+/*----------------------------------------------------------------*/
+
+macro_rules! def_mask {
+    ($mask:ident, $ty:ty) => {
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        pub struct $mask($ty);
+
+        impl $mask {
+            #[inline]
+            pub fn expand_inner(&mut self) {}
+
+            #[inline]
+            pub fn widen(self) -> $mask {
+                self
+            }
+
+            #[inline]
+            pub fn to_bitmask(self) -> $ty {
+                self.0
+            }
+
+            #[inline]
+            fn has(self, index: usize) -> bool {
+                (self.0 >> index) & 1 != 0
+            }
+        }
+
+        impl From<$ty> for $mask {
+            #[inline]
+            fn from(raw: $ty) -> Self {
+                Self(raw)
+            }
+        }
+
+        impl Not for $mask {
+            type Output = Self;
+
+            #[inline]
+            fn not(self) -> Self {
+                Self(!self.0)
+            }
+        }
+
+        impl BitAnd for $mask {
+            type Output = Self;
+
+            #[inline]
+            fn bitand(self, other: Self) -> Self {
+                Self(self.0 & other.0)
+            }
+        }
+
+        impl BitOr for $mask {
+            type Output = Self;
+
+            #[inline]
+            fn bitor(self, other: Self) -> Self {
+                Self(self.0 | other.0)
+            }
+        }
+
+        impl BitXor for $mask {
+            type Output = Self;
+
+            #[inline]
+            fn bitxor(self, other: Self) -> Self {
+                Self(self.0 ^ other.0)
+            }
+        }
+
+        impl BitAnd<$ty> for $mask {
+            type Output = Self;
+
+            #[inline]
+            fn bitand(self, other: $ty) -> Self {
+                Self(self.0 & other)
+            }
+        }
+
+        impl BitOr<$ty> for $mask {
+            type Output = Self;
+
+            #[inline]
+            fn bitor(self, other: $ty) -> Self {
+                Self(self.0 | other)
+            }
+        }
+
+        impl BitXor<$ty> for $mask {
+            type Output = Self;
+
+            #[inline]
+            fn bitxor(self, other: $ty) -> Self {
+                Self(self.0 ^ other)
+            }
+        }
+
+        impl BitAndAssign for $mask {
+            #[inline]
+            fn bitand_assign(&mut self, other: Self) {
+                self.0 &= other.0;
+            }
+        }
+
+        impl BitOrAssign for $mask {
+            #[inline]
+            fn bitor_assign(&mut self, other: Self) {
+                self.0 |= other.0;
+            }
+        }
+
+        impl BitXorAssign for $mask {
+            #[inline]
+            fn bitxor_assign(&mut self, other: Self) {
+                self.0 ^= other.0;
+            }
+        }
+
+        impl BitAndAssign<$ty> for $mask {
+            #[inline]
+            fn bitand_assign(&mut self, other: $ty) {
+                self.0 &= other;
+            }
+        }
+
+        impl BitOrAssign<$ty> for $mask {
+            #[inline]
+            fn bitor_assign(&mut self, other: $ty) {
+                self.0 |= other;
+            }
+        }
+
+        impl BitXorAssign<$ty> for $mask {
+            #[inline]
+            fn bitxor_assign(&mut self, other: $ty) {
+                self.0 ^= other;
+            }
+        }
+    };
+
+    ($mask:ident, $ty:ty, $next_mask:ident, $next_ty:ty, $concat_shift:expr) => {
+        def_mask!($mask, $ty);
+
+        impl $mask {
+            #[inline]
+            pub fn concat(self, other: $mask) -> $next_mask {
+                $next_mask(self.0 as $next_ty | ((other.0 as $next_ty) << $concat_shift))
+            }
+        }
+    };
+}
+
+/*----------------------------------------------------------------*/
+
+def_mask!(Mask8, u8, Mask16, u16, 8);
+def_mask!(Mask16, u16, Mask32, u32, 16);
+def_mask!(Mask32, u32, Mask64, u64, 32);
+def_mask!(Mask64, u64);
+
+/*----------------------------------------------------------------*/
+pub type Mask8x16 = Mask16;
+pub type Mask16x8 = Mask8;
+pub type Mask32x4 = Mask8;
+pub type Mask64x2 = Mask8;
+
+pub type Mask8x32 = Mask32;
+pub type Mask16x16 = Mask16;
+pub type Mask32x8 = Mask8;
+pub type Mask64x4 = Mask8;
+
+pub type Mask8x64 = Mask64;
+pub type Mask16x32 = Mask32;
+pub type Mask32x16 = Mask16;
+pub type Mask64x8 = Mask8;
+
+pub type Mask16x64 = Mask64;
+
+/*----------------------------------------------------------------*/
+
+macro_rules! def_vec {
+    ($vec:ident, $elem_ty:ty, $len:expr) => {
+        #[derive(Debug, Copy, Clone)]
+        #[repr(transparent)]
+        pub struct $vec([$elem_ty; $len]);
+
+        impl $vec {
+            #[inline]
+            pub unsafe fn load<T>(src: *const T) -> $vec {
+                unsafe { $vec(ptr::read_unaligned(src.cast())) }
+            }
+
+            #[inline]
+            pub unsafe fn store<T>(self, dest: *mut T) {
+                unsafe { ptr::write_unaligned(dest.cast(), self.0) }
+            }
+
+            #[inline]
+            pub fn splat(value: $elem_ty) -> $vec {
+                $vec([value; $len])
+            }
+
+            #[inline]
+            fn zip(a: $vec, b: $vec, f: impl Fn($elem_ty, $elem_ty) -> $elem_ty) -> $vec {
+                $vec(core::array::from_fn(|i| f(a.0[i], b.0[i])))
+            }
+        }
+
+        impl From<[$elem_ty; $len]> for $vec {
+            #[inline]
+            fn from(arr: [$elem_ty; $len]) -> Self {
+                $vec(arr)
+            }
+        }
+
+        impl BitAnd for $vec {
+            type Output = Self;
+
+            #[inline]
+            fn bitand(self, other: Self) -> Self {
+                $vec::zip(self, other, |a, b| a & b)
+            }
+        }
+
+        impl BitOr for $vec {
+            type Output = Self;
+
+            #[inline]
+            fn bitor(self, other: Self) -> Self {
+                $vec::zip(self, other, |a, b| a | b)
+            }
+        }
+
+        impl BitXor for $vec {
+            type Output = Self;
+
+            #[inline]
+            fn bitxor(self, other: Self) -> Self {
+                $vec::zip(self, other, |a, b| a ^ b)
+            }
+        }
+
+        impl BitAndAssign for $vec {
+            #[inline]
+            fn bitand_assign(&mut self, other: Self) {
+                *self = *self & other;
+            }
+        }
+
+        impl BitOrAssign for $vec {
+            #[inline]
+            fn bitor_assign(&mut self, other: Self) {
+                *self = *self | other;
+            }
+        }
+
+        impl BitXorAssign for $vec {
+            #[inline]
+            fn bitxor_assign(&mut self, other: Self) {
+                *self = *self ^ other;
+            }
+        }
+    };
+}
+
+macro_rules! impl_conv {
+    ($vec:ty, $($conv_fn:ident => $other_ty:ident;)*) => {
+        impl $vec {$(
+            #[inline]
+            pub fn $conv_fn(self) -> $other_ty {
+                unsafe { core::mem::transmute(self) }
+            }
+        )*}
+    }
+}
+
+macro_rules! impl_cmp {
+    ($vec:ident, $elem_ty:ty, $mask_ty:ident, $bits_ty:ty) => {
+        impl $vec {
+            #[inline]
+            fn cmp(a: $vec, b: $vec, f: impl Fn($elem_ty, $elem_ty) -> bool) -> $mask_ty {
+                let mut bits: $bits_ty = 0;
+                for i in 0..a.0.len() {
+                    bits |= (f(a.0[i], b.0[i]) as $bits_ty) << i;
+                }
+
+                $mask_ty::from(bits)
+            }
+
+            #[inline]
+            pub fn eq(a: $vec, b: $vec) -> $mask_ty {
+                $vec::cmp(a, b, |a, b| a == b)
+            }
+
+            #[inline]
+            pub fn neq(a: $vec, b: $vec) -> $mask_ty {
+                $vec::cmp(a, b, |a, b| a != b)
+            }
+
+            #[inline]
+            pub fn test(a: $vec, b: $vec) -> $mask_ty {
+                $vec::cmp(a, b, |a, b| a & b != 0)
+            }
+
+            #[inline]
+            pub fn testn(a: $vec, b: $vec) -> $mask_ty {
+                $vec::cmp(a, b, |a, b| a & b == 0)
+            }
+
+            /*----------------------------------------------------------------*/
+
+            #[inline]
+            pub fn zero(self) -> $mask_ty {
+                $vec::eq(self, $vec::splat(0))
+            }
+
+            #[inline]
+            pub fn nonzero(self) -> $mask_ty {
+                $vec::neq(self, $vec::splat(0))
+            }
+
+            #[inline]
+            pub fn msb(self) -> $mask_ty {
+                $vec::cmp(self, self, |a, _| a.leading_zeros() == 0)
+            }
+        }
+    };
+}
+
+macro_rules! impl_select {
+    ($vec:ident, $mask_ty:ty) => {
+        impl $vec {
+            #[inline]
+            pub fn mask(self, mask: $mask_ty) -> $vec {
+                $vec(core::array::from_fn(|i| {
+                    if mask.has(i) { self.0[i] } else { 0 }
+                }))
+            }
+
+            #[inline]
+            pub fn blend(a: $vec, b: $vec, mask: $mask_ty) -> $vec {
+                $vec(core::array::from_fn(|i| {
+                    if mask.has(i) { b.0[i] } else { a.0[i] }
+                }))
+            }
+
+            #[inline]
+            pub fn compress(self, mask: $mask_ty) -> $vec {
+                let mut result = $vec::splat(0);
+                let mut cursor = 0;
+
+                for i in 0..self.0.len() {
+                    if mask.has(i) {
+                        result.0[cursor] = self.0[i];
+                        cursor += 1;
+                    }
+                }
+
+                result
+            }
+
+            #[inline]
+            pub unsafe fn compress_store<T>(self, mask: $mask_ty, dest: *mut T) {
+                unsafe { self.compress(mask).store(dest) }
+            }
+        }
+    };
+    ($vec:ident, $mask_ty:ty, permute) => {
+        impl_select!($vec, $mask_ty);
+
+        impl $vec {
+            #[inline]
+            pub fn permute(self, index: $vec) -> $vec {
+                let len = self.0.len();
+                $vec(core::array::from_fn(|i| {
+                    self.0[index.0[i] as usize & (len - 1)]
+                }))
+            }
+
+            #[inline]
+            pub fn shuffle(self, index: $vec) -> $vec {
+                $vec(core::array::from_fn(|i| {
+                    if index.0[i] & 0x80 != 0 {
+                        0
+                    } else {
+                        self.0[(i & !15) | (index.0[i] & 15) as usize]
+                    }
+                }))
+            }
+        }
+    };
+}
+
+macro_rules! impl_extract {
+    ($vec:ident, $($extract_fn:ident => $other_ty:ident, $len:expr;)*) => {
+        impl $vec {$(
+            #[inline]
+            pub fn $extract_fn<const INDEX: i32>(self) -> $other_ty {
+                let start = INDEX as usize * $len;
+                $other_ty(self.0[start..start + $len].try_into().unwrap())
+            }
+        )*}
+    };
+}
+
+macro_rules! impl_broadcast {
+    ($vec:ident, $($broadcast_fn:ident => $other_ty:ident;)*) => {
+        impl $vec {$(
+            #[inline]
+            pub fn $broadcast_fn(self) -> $other_ty {
+                $other_ty(core::array::from_fn(|i| self.0[i % self.0.len()]))
+            }
+        )*}
+    };
+}
+
+macro_rules! impl_zero_ext {
+    ($vec:ident => $other_ty:ident) => {
+        impl $vec {
+            #[inline]
+            pub fn zero_ext(self) -> $other_ty {
+                $other_ty(core::array::from_fn(|i| self.0[i].into()))
+            }
+        }
+    };
+}
+
+macro_rules! impl_element {
+    ($vec:ident, $elem_ty:ty) => {
+        impl $vec {
+            #[inline]
+            pub fn extract<const INDEX: i32>(self) -> $elem_ty {
+                self.0[INDEX as usize]
+            }
+        }
+    };
+}
+
+macro_rules! impl_shift {
+    ($vec:ident, $elem_ty:ty) => {
+        impl $vec {
+            #[inline]
+            pub fn shl<const SHIFT: u32>(self) -> $vec {
+                $vec(self.0.map(|x| x.checked_shl(SHIFT).unwrap_or(0)))
+            }
+
+            #[inline]
+            pub fn shlv(self, shift: $vec) -> $vec {
+                $vec::zip(self, shift, |x, s| x.checked_shl(s as u32).unwrap_or(0))
+            }
+
+            #[inline]
+            pub fn shr<const SHIFT: u32>(self) -> $vec {
+                $vec(self.0.map(|x| x.checked_shr(SHIFT).unwrap_or(0)))
+            }
+
+            #[inline]
+            pub fn shrv(self, shift: $vec) -> $vec {
+                $vec::zip(self, shift, |x, s| x.checked_shr(s as u32).unwrap_or(0))
+            }
+        }
+    };
+}
+
+/*----------------------------------------------------------------*/
+
+def_vec!(u8x16, u8, 16);
+impl_conv! {
+    u8x16,
+    to_u16x8 => u16x8;
+    to_u32x4 => u32x4;
+    to_u64x2 => u64x2;
+}
+impl_cmp!(u8x16, u8, Mask8x16, u16);
+impl_select!(u8x16, Mask8x16, permute);
+impl_element!(u8x16, u8);
+impl_broadcast! {
+    u8x16,
+    broadcast32 => u8x32;
+    broadcast64 => u8x64;
+}
+impl_zero_ext!(u8x16 => u16x16);
+impl u8x16 {
+    #[inline]
+    pub fn findset(self, needles: u8x16, needle_count: usize) -> u16 {
+        let needles = &needles.0[..needle_count.min(16)];
+        let mut bits = 0;
+
+        for (i, value) in self.0.iter().enumerate() {
+            bits |= (needles.contains(value) as u16) << i;
+        }
+
+        bits
+    }
+}
+
+def_vec!(u16x8, u16, 8);
+impl_conv! {
+    u16x8,
+    to_u8x16 => u8x16;
+    to_u32x4 => u32x4;
+    to_u64x2 => u64x2;
+}
+impl_cmp!(u16x8, u16, Mask16x8, u8);
+impl_select!(u16x8, Mask16x8);
+impl_element!(u16x8, u16);
+impl_broadcast! {
+    u16x8,
+    broadcast16 => u16x16;
+    broadcast32 => u16x32;
+}
+impl_zero_ext!(u16x8 => u32x8);
+impl_shift!(u16x8, u16);
+
+def_vec!(u32x4, u32, 4);
+impl_conv! {
+    u32x4,
+    to_u8x16 => u8x16;
+    to_u16x8 => u16x8;
+    to_u64x2 => u64x2;
+}
+impl_cmp!(u32x4, u32, Mask32x4, u8);
+impl_select!(u32x4, Mask32x4);
+impl_element!(u32x4, u32);
+impl_broadcast! {
+    u32x4,
+    broadcast8 => u32x8;
+    broadcast16 => u32x16;
+}
+impl_zero_ext!(u32x4 => u64x4);
+
+def_vec!(u64x2, u64, 2);
+impl_conv! {
+    u64x2,
+    to_u8x16 => u8x16;
+    to_u16x8 => u16x8;
+    to_u32x4 => u32x4;
+}
+impl_cmp!(u64x2, u64, Mask64x2, u8);
+impl_select!(u64x2, Mask64x2);
+impl_element!(u64x2, u64);
+impl_broadcast! {
+    u64x2,
+    broadcast4 => u64x4;
+    broadcast8 => u64x8;
+}
+
+/*----------------------------------------------------------------*/
+
+def_vec!(u8x32, u8, 32);
+impl_conv! {
+    u8x32,
+    to_u16x16 => u16x16;
+    to_u32x8 => u32x8;
+    to_u64x4 => u64x4;
+}
+impl_cmp!(u8x32, u8, Mask8x32, u32);
+impl_select!(u8x32, Mask8x32, permute);
+impl_element!(u8x32, u8);
+impl_extract! {
+    u8x32,
+    extract16 => u8x16, 16;
+}
+impl_broadcast! {
+    u8x32,
+    broadcast64 => u8x64;
+}
+impl_zero_ext!(u8x32 => u16x32);
+
+def_vec!(u16x16, u16, 16);
+impl_conv! {
+    u16x16,
+    to_u8x32 => u8x32;
+    to_u32x8 => u32x8;
+    to_u64x4 => u64x4;
+}
+impl_cmp!(u16x16, u16, Mask16x16, u16);
+impl_select!(u16x16, Mask16x16);
+impl_element!(u16x16, u16);
+impl_extract! {
+    u16x16,
+    extract8 => u16x8, 8;
+}
+impl_broadcast! {
+    u16x16,
+    broadcast32 => u16x32;
+}
+impl_zero_ext!(u16x16 => u32x16);
+impl_shift!(u16x16, u16);
+
+def_vec!(u32x8, u32, 8);
+impl_conv! {
+    u32x8,
+    to_u8x32 => u8x32;
+    to_u16x16 => u16x16;
+    to_u64x4 => u64x4;
+}
+impl_cmp!(u32x8, u32, Mask32x8, u8);
+impl_select!(u32x8, Mask32x8);
+impl_element!(u32x8, u32);
+impl_extract! {
+    u32x8,
+    extract4 => u32x4, 4;
+}
+impl_broadcast! {
+    u32x8,
+    broadcast16 => u32x16;
+}
+impl_zero_ext!(u32x8 => u64x8);
+
+def_vec!(u64x4, u64, 4);
+impl_conv! {
+    u64x4,
+    to_u8x32 => u8x32;
+    to_u16x16 => u16x16;
+    to_u32x8 => u32x8;
+}
+impl_cmp!(u64x4, u64, Mask64x4, u8);
+impl_select!(u64x4, Mask64x4);
+impl_element!(u64x4, u64);
+impl_extract! {
+    u64x4,
+    extract2 => u64x2, 2;
+}
+impl_broadcast! {
+    u64x4,
+    broadcast8 => u64x8;
+}
+
+/*----------------------------------------------------------------*/
+
+def_vec!(u8x64, u8, 64);
+impl_conv! {
+    u8x64,
+    to_u16x32 => u16x32;
+    to_u32x16 => u32x16;
+    to_u64x8 => u64x8;
+}
+impl_cmp!(u8x64, u8, Mask8x64, u64);
+impl_select!(u8x64, Mask8x64, permute);
+impl_extract! {
+    u8x64,
+    extract16 => u8x16, 16;
+    extract32 => u8x32, 32;
+}
+impl_zero_ext!(u8x64 => u16x64);
+impl u8x64 {
+    #[inline]
+    pub fn flip_rays(self) -> u8x64 {
+        u8x64(core::array::from_fn(|i| self.0[i ^ 32]))
+    }
+
+    #[inline]
+    pub fn extend_rays(self) -> u8x64 {
+        let mut result = self;
+        for ray in result.0.chunks_exact_mut(8) {
+            let value = ray.iter().fold(0, |acc, &x| acc | x);
+            ray.fill(value);
+        }
+
+        result
+    }
+}
+
+def_vec!(u16x32, u16, 32);
+impl_conv! {
+    u16x32,
+    to_u8x64 => u8x64;
+    to_u32x16 => u32x16;
+    to_u64x8 => u64x8;
+}
+impl_cmp!(u16x32, u16, Mask16x32, u32);
+impl_select!(u16x32, Mask16x32);
+impl_extract! {
+    u16x32,
+    extract8 => u16x8, 8;
+    extract16 => u16x16, 16;
+}
+impl_shift!(u16x32, u16);
+
+def_vec!(u32x16, u32, 16);
+impl_conv! {
+    u32x16,
+    to_u8x64 => u8x64;
+    to_u16x32 => u16x32;
+    to_u64x8 => u64x8;
+}
+impl_cmp!(u32x16, u32, Mask32x16, u16);
+impl_select!(u32x16, Mask32x16);
+impl_extract! {
+    u32x16,
+    extract4 => u32x4, 4;
+    extract8 => u32x8, 8;
+}
+
+def_vec!(u64x8, u64, 8);
+impl_conv! {
+    u64x8,
+    to_u8x64 => u8x64;
+    to_u16x32 => u16x32;
+    to_u32x16 => u32x16;
+}
+impl_cmp!(u64x8, u64, Mask64x8, u8);
+impl_select!(u64x8, Mask64x8);
+impl_extract! {
+    u64x8,
+    extract2 => u64x2, 2;
+    extract4 => u64x4, 4;
+}
+
+/*----------------------------------------------------------------*/
+
+def_vec!(u16x64, u16, 64);
+impl_cmp!(u16x64, u16, Mask16x64, u64);
+impl_select!(u16x64, Mask16x64);
+impl_extract! {
+    u16x64,
+    extract16 => u16x16, 16;
+    extract32 => u16x32, 32;
+}
+impl_shift!(u16x64, u16);
+
+/*----------------------------------------------------------------*/
+
+def_vec!(i16x32, i16, 32);
+impl i16x32 {
+    #[inline]
+    pub fn clamp(self, min: i16x32, max: i16x32) -> i16x32 {
+        i16x32(core::array::from_fn(|i| {
+            self.0[i].max(min.0[i]).min(max.0[i])
+        }))
+    }
+
+    #[inline]
+    pub fn madd(self, rhs: i16x32) -> i32x16 {
+        i32x16(core::array::from_fn(|i| {
+            let lo = self.0[2 * i] as i32 * rhs.0[2 * i] as i32;
+            let hi = self.0[2 * i + 1] as i32 * rhs.0[2 * i + 1] as i32;
+            lo.wrapping_add(hi)
+        }))
+    }
+}
+impl Add for i16x32 {
+    type Output = i16x32;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        i16x32::zip(self, rhs, i16::wrapping_add)
+    }
+}
+impl Sub for i16x32 {
+    type Output = i16x32;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        i16x32::zip(self, rhs, i16::wrapping_sub)
+    }
+}
+impl Mul for i16x32 {
+    type Output = i16x32;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        i16x32::zip(self, rhs, i16::wrapping_mul)
+    }
+}
+impl AddAssign for i16x32 {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+impl SubAssign for i16x32 {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+def_vec!(i32x16, i32, 16);
+impl i32x16 {
+    #[inline]
+    pub fn reduce_sum(self) -> i32 {
+        self.0.iter().fold(0, |acc, &x| acc.wrapping_add(x))
+    }
+}
+impl Add for i32x16 {
+    type Output = i32x16;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        i32x16::zip(self, rhs, i32::wrapping_add)
+    }
+}
+impl Sub for i32x16 {
+    type Output = i32x16;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        i32x16::zip(self, rhs, i32::wrapping_sub)
+    }
+}
+impl AddAssign for i32x16 {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+impl SubAssign for i32x16 {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
@@ -21,6 +21,7 @@ mod search {
     mod move_picker;
     mod search;
     mod searcher;
+    mod skill;
     mod time;
     mod ttable;
     mod weights;
@@ -31,6 +32,7 @@ mod search {
     pub use move_picker::*;
     pub use search::*;
     pub use searcher::*;
+    pub use skill::*;
     pub use time::*;
     pub use ttable::*;
     pub use weights::*;
@@ -41,29 +43,53 @@ mod util {
     mod atomic_instant;
     mod batched_atomic;
     mod command_channel;
+    #[cfg(test)]
+    mod temp_file;
 
     pub use atomic_instant::*;
     pub use batched_atomic::*;
     pub use command_channel::*;
+    #[cfg(test)]
+    pub use temp_file::*;
 }
 
+mod analyze;
 mod attacks;
 mod bench;
+mod book;
 mod datagen;
 mod engine;
+mod epd;
+mod json;
+mod options;
 mod position;
 mod score;
+#[cfg(feature = "tune")]
+mod spsa;
 mod syzygy;
+mod tournament;
 mod uci;
 mod wdl;
+mod wdlfit;
+mod xboard;
 
+pub use analyze::*;
 pub use attacks::*;
+pub use book::*;
+pub use datagen::*;
 pub use engine::*;
+pub use json::*;
 pub use nnue::*;
+pub use options::*;
 pub use position::*;
 pub use score::*;
 pub use search::*;
+#[cfg(feature = "tune")]
+pub use spsa::*;
 pub use syzygy::*;
+pub use tournament::*;
 pub use uci::*;
 pub use util::*;
 pub use wdl::*;
+pub use wdlfit::*;
+pub use xboard::*;
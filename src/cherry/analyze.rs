@@ -0,0 +1,312 @@
+use std::{fs, path::Path, sync::mpsc};
+
+use crate::*;
+
+/*----------------------------------------------------------------*/
+
+pub const DEFAULT_ANALYZE_MOVETIME: u64 = 1000;
+
+//Drops in expected score (from 0 to 1) of the side that moved
+const INACCURACY_DROP: f64 = 0.1;
+const MISTAKE_DROP: f64 = 0.2;
+const BLUNDER_DROP: f64 = 0.3;
+
+const NAG_MISTAKE: u8 = 2;
+const NAG_BLUNDER: u8 = 4;
+const NAG_INACCURACY: u8 = 6;
+
+#[inline]
+pub fn default_analysis_path(pgn: &str) -> String {
+    Path::new(pgn)
+        .with_extension("analyzed.pgn")
+        .to_string_lossy()
+        .into_owned()
+}
+
+/*----------------------------------------------------------------*/
+
+#[derive(Debug, Clone)]
+struct PositionAnalysis {
+    board: Board,
+    score: Score,
+    pv: Vec<Move>,
+}
+
+//Indexed by the color of the side that moved
+#[derive(Debug, Copy, Clone, Default)]
+pub struct AnalysisStats {
+    pub inaccuracies: [usize; Color::COUNT],
+    pub mistakes: [usize; Color::COUNT],
+    pub blunders: [usize; Color::COUNT],
+}
+
+#[inline]
+fn expected_score(score: Score, material: i16) -> f64 {
+    let (w, l) = wdl_model(score, material);
+    (1000.0 + w as f64 - l as f64) / 2000.0
+}
+
+impl Engine {
+    fn analyze_position(
+        &mut self,
+        pos: &Position,
+        limits: &[SearchLimit],
+        options: EngineOptions,
+    ) -> PositionAnalysis {
+        let board = pos.board().clone();
+        match board.status() {
+            BoardStatus::Checkmate => {
+                return PositionAnalysis {
+                    board,
+                    score: Score::mated(0),
+                    pv: Vec::new(),
+                };
+            }
+            BoardStatus::Draw => {
+                return PositionAnalysis {
+                    board,
+                    score: Score::ZERO,
+                    pv: Vec::new(),
+                };
+            }
+            BoardStatus::Ongoing if pos.insufficient_material() => {
+                return PositionAnalysis {
+                    board,
+                    score: Score::ZERO,
+                    pv: Vec::new(),
+                };
+            }
+            BoardStatus::Ongoing => {}
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        self.searcher.search(
+            pos.clone(),
+            limits.to_vec(),
+            options,
+            SearchInfo::new(sender),
+        );
+        self.searcher.wait();
+
+        let mut pv = receiver
+            .try_iter()
+            .filter_map(|event| match event {
+                SearchEvent::Info(report) if report.pv_index == 0 => Some(report.pv),
+                _ => None,
+            })
+            .last()
+            .unwrap_or_default();
+
+        let best_move = self.searcher.shared.best_move();
+        if pv.first().copied() != best_move {
+            pv = best_move.into_iter().collect();
+        }
+
+        //only keep the legal prefix, in case the tail of the PV came from a stale TT entry
+        let mut pv_board = board.clone();
+        let legal = pv
+            .iter()
+            .take_while(|&&mv| {
+                let legal = pv_board.gen_moves().contains(&mv);
+                if legal {
+                    pv_board.make_move(mv);
+                }
+
+                legal
+            })
+            .count();
+        pv.truncate(legal);
+
+        PositionAnalysis {
+            board,
+            score: self.searcher.shared.best_score(),
+            pv,
+        }
+    }
+
+    /*
+    Comment of a position from White's perspective,
+    as `[%eval <pawns|#mate>] [%wdl <win>/<draw>/<loss>]` with the WDL in per mille.
+    Non-mate scores also get `[%cp_raw <centipawns>]`, which is never normalised
+    so that the output can be used by `fitwdl`.
+    */
+    fn analysis_comment(&self, board: &Board, score: Score) -> String {
+        let material = board.classic_material();
+        let (score, (w, l)) = match board.stm() {
+            Color::White => (score, wdl_model(score, material)),
+            Color::Black => {
+                let (w, l) = wdl_model(score, material);
+                (-score, (l, w))
+            }
+        };
+
+        let eval = match score.mate_in() {
+            Some(ply) => format!("#{}", (ply + ply.signum()) / 2),
+            None if self.options.score_normalisation => {
+                format!("{:.2}", score.normalise(material).0 as f64 / 100.0)
+            }
+            None => format!("{:.2}", score.0 as f64 / 100.0),
+        };
+
+        let comment = format!("[%eval {eval}] [%wdl {w}/{}/{l}]", 1000 - w - l);
+        match score.mate_in() {
+            Some(_) => comment,
+            None => format!("{comment} [%cp_raw {}]", score.0),
+        }
+    }
+
+    fn analyze_game(
+        &mut self,
+        game: &mut PgnGame,
+        limits: &[SearchLimit],
+        options: EngineOptions,
+    ) -> AnalysisStats {
+        self.searcher.newgame();
+
+        let mut pos = Position::new(game.start.clone());
+        let mut analyses = vec![self.analyze_position(&pos, limits, options)];
+        for pgn_move in &game.moves {
+            pos.make_move(pgn_move.mv);
+            pos.reset_nnue();
+            analyses.push(self.analyze_position(&pos, limits, options));
+        }
+
+        let mut stats = AnalysisStats::default();
+        for (i, pgn_move) in game.moves.iter_mut().enumerate() {
+            let (before, after) = (&analyses[i], &analyses[i + 1]);
+            let mover = before.board.stm() as usize;
+
+            if after.board.status() != BoardStatus::Checkmate {
+                pgn_move
+                    .comments
+                    .push(self.analysis_comment(&after.board, after.score));
+            }
+
+            let Some(&best_move) = before.pv.first() else {
+                continue;
+            };
+
+            if best_move == pgn_move.mv {
+                continue;
+            }
+
+            let drop = expected_score(before.score, before.board.classic_material())
+                - expected_score(-after.score, after.board.classic_material());
+            let nag = if drop >= BLUNDER_DROP {
+                stats.blunders[mover] += 1;
+                Some(NAG_BLUNDER)
+            } else if drop >= MISTAKE_DROP {
+                stats.mistakes[mover] += 1;
+                Some(NAG_MISTAKE)
+            } else if drop >= INACCURACY_DROP {
+                stats.inaccuracies[mover] += 1;
+                Some(NAG_INACCURACY)
+            } else {
+                None
+            };
+
+            let Some(nag) = nag else {
+                continue;
+            };
+
+            let mut variation = before
+                .pv
+                .iter()
+                .map(|&mv| PgnMove::new(mv))
+                .collect::<Vec<_>>();
+            let mut board = before.board.clone();
+            board.make_move(best_move);
+            variation[0]
+                .comments
+                .push(self.analysis_comment(&board, -before.score));
+
+            pgn_move.nags.push(nag);
+            pgn_move.variations.push(variation);
+        }
+
+        game.set_tag("Annotator", &format!("Cherry {ENGINE_VERSION}"));
+        stats
+    }
+
+    pub fn analyze(&mut self, pgn: &str, limits: Vec<SearchLimit>, output: &str) {
+        if self.searcher.is_searching() {
+            self.message("Not Allowed to analyze while Searching");
+            return;
+        }
+
+        let contents = match fs::read_to_string(pgn) {
+            Ok(contents) => contents,
+            Err(e) => {
+                self.message(format!("Failed to read `{pgn}`: {e}"));
+                return;
+            }
+        };
+
+        let mut games = Vec::new();
+        for game in PgnReader::new(&contents) {
+            match game {
+                Ok(game) => games.push(game),
+                Err(e) => {
+                    self.message(format!("Failed to parse `{pgn}`: {e}"));
+                    return;
+                }
+            }
+        }
+
+        let options = EngineOptions {
+            multipv: 1,
+            skill_level: MAX_SKILL_LEVEL,
+            ponder: false,
+            ..self.options
+        };
+
+        let num_games = games.len();
+        let mut annotated = String::new();
+        for (i, game) in games.iter_mut().enumerate() {
+            let stats = self.analyze_game(game, &limits, options);
+            self.report_analysis(game, &stats, i + 1, num_games);
+
+            if i > 0 {
+                annotated.push('\n');
+            }
+            annotated.push_str(&game.to_pgn());
+        }
+
+        self.searcher.newgame();
+        match fs::write(output, annotated) {
+            Ok(()) => self.message(format!("Wrote {num_games} analyzed games to `{output}`")),
+            Err(e) => self.message(format!("Failed to write `{output}`: {e}")),
+        }
+    }
+
+    fn report_analysis(&self, game: &PgnGame, stats: &AnalysisStats, index: usize, total: usize) {
+        let white = game.tag("White").unwrap_or("?");
+        let black = game.tag("Black").unwrap_or("?");
+        let (w, b) = (Color::White as usize, Color::Black as usize);
+
+        if self.options.json {
+            let mut output = JsonObject::new("analyze");
+            output
+                .num("game", index)
+                .num("games", total)
+                .str("white", white)
+                .str("black", black)
+                .num_array("inaccuracies", stats.inaccuracies)
+                .num_array("mistakes", stats.mistakes)
+                .num_array("blunders", stats.blunders);
+
+            println!("{output}");
+            return;
+        }
+
+        println!(
+            "info string analyze game {index}/{total} {white} - {black} inaccuracies {}/{} mistakes {}/{} blunders {}/{}",
+            stats.inaccuracies[w],
+            stats.inaccuracies[b],
+            stats.mistakes[w],
+            stats.mistakes[b],
+            stats.blunders[w],
+            stats.blunders[b],
+        );
+    }
+}
@@ -3,6 +3,16 @@ use crate::*;
 /*----------------------------------------------------------------*/
 
 include!(concat!(env!("OUT_DIR"), "/slider_moves.rs"));
+include!(concat!(env!("OUT_DIR"), "/cuckoo.rs"));
+
+//The reversible move that changes the hash by `key`, if there is one
+#[inline]
+pub fn cuckoo_move(key: u64) -> Option<Move> {
+    [cuckoo_h1(key), cuckoo_h2(key)]
+        .into_iter()
+        .find(|&i| CUCKOO_KEYS[i] == key)
+        .map(|i| Move::from_bits(CUCKOO_MOVES[i]))
+}
 
 #[inline]
 pub fn bishop_moves(sq: Square, blockers: Bitboard) -> Bitboard {
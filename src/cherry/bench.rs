@@ -71,7 +71,7 @@ impl Engine {
                 self.pos.clone(),
                 limits.clone(),
                 self.options,
-                SearchInfo::None,
+                SearchInfo::none(),
             );
             self.searcher.wait();
 
@@ -86,6 +86,18 @@ impl Engine {
             0
         };
 
+        if self.options.json {
+            let mut output = JsonObject::new("bench");
+            output
+                .num("depth", depth)
+                .num("nodes", total_nodes)
+                .num("time", total_time.as_millis())
+                .num("nps", nps);
+
+            println!("{output}");
+            return;
+        }
+
         println!("nodes {total_nodes} time {total_time:.2?} nps {nps}");
     }
 }
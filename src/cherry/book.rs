@@ -0,0 +1,336 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use rand::{Rng, RngExt};
+
+use crate::*;
+
+/*----------------------------------------------------------------*/
+
+pub const DEFAULT_BOOK_DEPTH: u16 = 40;
+pub const DEFAULT_BOOK_VARIETY: u8 = 50;
+pub const DEFAULT_MAKEBOOK_PLIES: usize = 30;
+
+/*----------------------------------------------------------------*/
+
+/*
+Polyglot book entry (16 bytes, big endian):
+- Bytes  0-7:  Polyglot key of the position
+- Bytes  8-9:  Move
+- Bytes 10-11: Weight
+- Bytes 12-15: Learn (unused)
+
+Move Bit Layout:
+- Bits 0-2: Target file
+- Bits 3-5: Target rank
+- Bits 6-8: Source file
+- Bits 9-11: Source rank
+- Bits 12-14: Promotion (0 = None, 1 = Knight, 2 = Bishop, 3 = Rook, 4 = Queen)
+
+Castling is encoded as the king capturing its own rook, like our own moves.
+*/
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub mv: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    pub const SIZE: usize = 16;
+
+    #[inline]
+    pub fn from_bytes(bytes: &[u8]) -> BookEntry {
+        BookEntry {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            mv: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    #[inline]
+    pub fn to_bytes(self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.mv.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+
+        bytes
+    }
+}
+
+const PROMOTIONS: [Option<Piece>; 5] = [
+    None,
+    Some(Piece::Knight),
+    Some(Piece::Bishop),
+    Some(Piece::Rook),
+    Some(Piece::Queen),
+];
+
+#[inline]
+pub fn encode_book_move(mv: Move) -> u16 {
+    let (src, dest) = (mv.src(), mv.dest());
+    let promotion = PROMOTIONS
+        .iter()
+        .position(|&piece| piece == mv.promotion())
+        .unwrap() as u16;
+
+    dest.file() as u16
+        | (dest.rank() as u16) << 3
+        | (src.file() as u16) << 6
+        | (src.rank() as u16) << 9
+        | promotion << 12
+}
+
+#[inline]
+pub fn decode_book_move(board: &Board, bits: u16) -> Option<Move> {
+    board
+        .gen_moves()
+        .iter()
+        .copied()
+        .find(|&mv| encode_book_move(mv) == bits)
+}
+
+/*----------------------------------------------------------------*/
+
+#[derive(thiserror::Error, Debug)]
+pub enum BookError {
+    #[error("I/O Error: `{0}`")]
+    Io(#[from] io::Error),
+    #[error("Not a Polyglot book: size of {0} bytes is not a multiple of 16")]
+    InvalidSize(usize),
+    #[error("Failed to parse PGN: `{0}`")]
+    InvalidPgn(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Book {
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Book, BookError> {
+        let bytes = fs::read(path)?;
+        if bytes.len() % BookEntry::SIZE != 0 {
+            return Err(BookError::InvalidSize(bytes.len()));
+        }
+
+        let mut entries = bytes
+            .chunks_exact(BookEntry::SIZE)
+            .map(BookEntry::from_bytes)
+            .collect::<Vec<_>>();
+
+        //books are supposed to be sorted already, but lookups rely on it
+        entries.sort_by_key(|entry| entry.key);
+
+        Ok(Book { entries })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), BookError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        for entry in &self.entries {
+            writer.write_all(&entry.to_bytes())?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /*----------------------------------------------------------------*/
+
+    #[inline]
+    pub fn entries(&self, board: &Board) -> &[BookEntry] {
+        let key = board.polyglot_hash();
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = self.entries.partition_point(|entry| entry.key <= key);
+
+        &self.entries[start..end]
+    }
+
+    pub fn moves(&self, board: &Board) -> Vec<(Move, u16)> {
+        let mut moves = self
+            .entries(board)
+            .iter()
+            .filter_map(|entry| Some((decode_book_move(board, entry.mv)?, entry.weight)))
+            .collect::<Vec<_>>();
+
+        moves.sort_by_key(|&(_, weight)| u16::MAX - weight);
+        moves
+    }
+
+    /*
+    Weighted random move selection.
+    At a variety of 0, the move with the highest weight is always picked.
+    Otherwise, each move is picked with a probability proportional to
+    its weight raised to `100 / variety`, so a variety of 100 follows
+    the weights of the book exactly.
+    Weights are relative to the highest one first, so that the power can't overflow.
+    */
+    pub fn pick(&self, board: &Board, variety: u8, rng: &mut impl Rng) -> Option<Move> {
+        let moves = self.moves(board);
+        let &(best, max_weight) = moves.first()?;
+        if variety == 0 || max_weight == 0 {
+            return Some(best);
+        }
+
+        let exponent = 100.0 / variety as f64;
+        let weights = moves
+            .iter()
+            .map(|&(_, weight)| (weight as f64 / max_weight as f64).powf(exponent))
+            .collect::<Vec<_>>();
+        let total = weights.iter().sum::<f64>();
+        if total <= 0.0 {
+            return Some(best);
+        }
+
+        let mut target = rng.random_range(0.0..total);
+        for (&(mv, _), weight) in moves.iter().zip(weights) {
+            if target < weight {
+                return Some(mv);
+            }
+
+            target -= weight;
+        }
+
+        Some(best)
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+/*
+Builds a Polyglot book from finished games, in the same way as Polyglot's `make-book`:
+every move played within the first `max_ply` plies scores 2 points for a win,
+1 for a draw and 0 for a loss, from the perspective of the side that played it.
+Moves that never scored a point are left out.
+*/
+#[derive(Debug, Clone)]
+pub struct BookBuilder {
+    max_ply: usize,
+    scores: HashMap<(u64, u16), u64>,
+    games: usize,
+}
+
+impl BookBuilder {
+    #[inline]
+    pub fn new(max_ply: usize) -> BookBuilder {
+        BookBuilder {
+            max_ply,
+            scores: HashMap::new(),
+            games: 0,
+        }
+    }
+
+    #[inline]
+    pub fn games(&self) -> usize {
+        self.games
+    }
+
+    pub fn add_game(&mut self, game: &PgnGame) {
+        let winner = match game.result {
+            GameResult::WhiteWin => Some(Color::White),
+            GameResult::BlackWin => Some(Color::Black),
+            GameResult::Draw => None,
+            GameResult::Unknown => return,
+        };
+
+        for (board, mv) in game.positions().into_iter().take(self.max_ply) {
+            let score = match winner {
+                Some(color) if color == board.stm() => 2,
+                Some(_) => 0,
+                None => 1,
+            };
+
+            *self
+                .scores
+                .entry((board.polyglot_hash(), encode_book_move(mv)))
+                .or_default() += score;
+        }
+
+        self.games += 1;
+    }
+
+    pub fn add_pgn(&mut self, pgn: &str) -> Result<(), BookError> {
+        for game in PgnReader::new(pgn) {
+            let game = game.map_err(|e| BookError::InvalidPgn(e.to_string()))?;
+            self.add_game(&game);
+        }
+
+        Ok(())
+    }
+
+    pub fn build(self) -> Book {
+        let max_score = self.scores.values().copied().max().unwrap_or(0);
+        let scale = max_score.div_ceil(u16::MAX as u64).max(1);
+
+        let mut entries = self
+            .scores
+            .into_iter()
+            .filter(|&(_, score)| score / scale > 0)
+            .map(|((key, mv), score)| BookEntry {
+                key,
+                mv,
+                weight: (score / scale) as u16,
+                learn: 0,
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| (entry.key, u16::MAX - entry.weight, entry.mv));
+
+        Book { entries }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::SmallRng};
+
+    use super::*;
+
+    #[test]
+    fn pick_extreme_weights() {
+        let board = Board::startpos();
+        let entry = |mv: &str, weight: u16| BookEntry {
+            key: board.polyglot_hash(),
+            mv: encode_book_move(Move::parse(&board, mv).unwrap()),
+            weight,
+            learn: 0,
+        };
+        let book = Book {
+            entries: vec![
+                entry("e2e4", u16::MAX),
+                entry("d2d4", 60000),
+                entry("c2c4", 0),
+            ],
+        };
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        for variety in [0, 1, 50, 100] {
+            for _ in 0..100 {
+                let mv = book.pick(&board, variety, &mut rng).unwrap();
+                assert_ne!(mv.to_string(), "c2c4");
+            }
+        }
+
+        //at a variety of 1, the weights are raised to the 100th power
+        let e4 = Move::parse(&board, "e2e4");
+        assert!((0..100).all(|_| book.pick(&board, 1, &mut rng) == e4));
+        assert_eq!(Book::default().pick(&board, 1, &mut rng), None);
+    }
+}
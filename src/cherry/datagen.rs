@@ -1,16 +1,150 @@
-use rand::{RngExt, SeedableRng, random_bool, rngs::SmallRng};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Write},
+    sync::{atomic::*, mpsc},
+};
+
+use pyrrhic_rs::WdlProbeResult;
+use rand::{RngExt, SeedableRng, rngs::SmallRng};
 
 use crate::*;
 
+/*----------------------------------------------------------------*/
+
+const WIN_ADJ_SCORE: i32 = 2500;
+const WIN_ADJ_PLIES: u16 = 4;
+const DRAW_ADJ_SCORE: i32 = 10;
+const DRAW_ADJ_PLIES: u16 = 10;
+const DRAW_ADJ_MIN_PLY: u16 = 80;
+const MAX_OPENING_SCORE: i32 = 1000;
+
+#[derive(Debug, Clone)]
+pub struct DatagenOptions {
+    pub games: usize,
+    pub threads: usize,
+    pub seed: u64,
+    pub nodes: u64,
+    pub dfrc: bool,
+    pub moves: usize,
+    pub output: String,
+}
+
+impl Default for DatagenOptions {
+    #[inline]
+    fn default() -> Self {
+        DatagenOptions {
+            games: 0,
+            threads: 1,
+            seed: 0,
+            nodes: 5000,
+            dfrc: false,
+            moves: 8,
+            output: String::from("data.bin"),
+        }
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+/*
+Bulletformat `ChessBoard` (32 bytes, little endian), always from the side to move's perspective:
+- Bytes  0-7:  Occupancy
+- Bytes  8-23: Pieces, one nibble per occupied square in LSB order (bit 3 set for the opponent)
+- Bytes 24-25: Score
+- Byte     26: Result (0 = Loss, 1 = Draw, 2 = Win)
+- Byte     27: King Square
+- Byte     28: Opponent King Square (flipped)
+- Bytes 29-31: Unused
+*/
+#[derive(Debug, Copy, Clone)]
+pub struct PackedBoard([u8; 32]);
+
+impl PackedBoard {
+    pub fn new(board: &Board, score: i16, result: u8) -> PackedBoard {
+        let stm = board.stm();
+        let flip = |bb: Bitboard| match stm {
+            Color::White => bb.0,
+            Color::Black => bb.0.swap_bytes(),
+        };
+
+        let us = flip(board.colors(stm));
+        let them = flip(board.colors(!stm));
+        let occ = us | them;
+
+        let mut pcs = [0u8; 16];
+        let mut remaining = occ;
+        let mut index = 0;
+        while remaining != 0 {
+            let bit = remaining & remaining.wrapping_neg();
+            let piece = Piece::ALL
+                .iter()
+                .position(|&piece| flip(board.pieces(piece)) & bit != 0)
+                .unwrap() as u8;
+            let color = (them & bit != 0) as u8;
+
+            pcs[index / 2] |= ((color << 3) | piece) << (4 * (index & 1));
+            remaining ^= bit;
+            index += 1;
+        }
+
+        let king = flip(board.colors(stm) & board.pieces(Piece::King)).trailing_zeros() as u8;
+        let opp_king =
+            flip(board.colors(!stm) & board.pieces(Piece::King)).trailing_zeros() as u8 ^ 56;
+
+        let mut bytes = [0u8; 32];
+        bytes[0..8].copy_from_slice(&occ.to_le_bytes());
+        bytes[8..24].copy_from_slice(&pcs);
+        bytes[24..26].copy_from_slice(&score.to_le_bytes());
+        bytes[26] = result;
+        bytes[27] = king;
+        bytes[28] = opp_king;
+
+        PackedBoard(bytes)
+    }
+
+    #[inline]
+    pub fn from_bytes(bytes: [u8; 32]) -> PackedBoard {
+        PackedBoard(bytes)
+    }
+
+    #[inline]
+    pub fn to_bytes(self) -> [u8; 32] {
+        self.0
+    }
+
+    #[inline]
+    pub fn score(&self) -> i16 {
+        i16::from_le_bytes([self.0[24], self.0[25]])
+    }
+
+    #[inline]
+    pub fn result(&self) -> u8 {
+        self.0[26]
+    }
+
+    //Same as `Board::classic_material`
+    pub fn classic_material(&self) -> i16 {
+        const VALUES: [i16; 8] = [1, 3, 3, 5, 9, 0, 0, 0];
+
+        let pieces = u64::from_le_bytes(self.0[0..8].try_into().unwrap()).count_ones() as usize;
+        (0..pieces)
+            .map(|i| VALUES[((self.0[8 + i / 2] >> (4 * (i & 1))) & 0b111) as usize])
+            .sum()
+    }
+}
+
+/*----------------------------------------------------------------*/
+
 #[inline]
-fn gen_opening(rng: &mut SmallRng, dfrc: bool, moves: usize) -> Option<Board> {
+pub fn gen_opening(rng: &mut SmallRng, dfrc: bool, moves: usize) -> Option<Board> {
     let mut board = if dfrc {
         Board::dfrc_startpos(rng.random_range(0..960), rng.random_range(0..960))
     } else {
         Board::startpos()
     };
 
-    let moves = moves + random_bool(0.5) as usize;
+    let moves = moves + rng.random_bool(0.5) as usize;
     for _ in 0..moves {
         let legal_moves = board.gen_moves();
         if legal_moves.is_empty() {
@@ -28,6 +162,146 @@ fn gen_opening(rng: &mut SmallRng, dfrc: bool, moves: usize) -> Option<Board> {
     Some(board)
 }
 
+fn search(
+    searcher: &mut Searcher,
+    pos: &Position,
+    nodes: u64,
+    options: EngineOptions,
+) -> (Move, Score) {
+    searcher.search(
+        pos.clone(),
+        vec![SearchLimit::Nodes(nodes)],
+        options,
+        SearchInfo::none(),
+    );
+    searcher.wait();
+
+    //Only called on ongoing positions, so the root always has a legal move to report
+    (
+        searcher.shared.best_move().unwrap(),
+        searcher.shared.best_score(),
+    )
+}
+
+fn play_game(
+    searcher: &mut Searcher,
+    rng: &mut SmallRng,
+    options: &DatagenOptions,
+) -> Vec<PackedBoard> {
+    let engine_options = EngineOptions {
+        soft_target: true,
+        frc: options.dfrc,
+        ..EngineOptions::default()
+    };
+
+    searcher.newgame();
+
+    let mut pos = loop {
+        let Some(board) = gen_opening(rng, options.dfrc, options.moves) else {
+            continue;
+        };
+
+        let pos = Position::new(board);
+        let (_, score) = search(searcher, &pos, 1000, engine_options);
+        if score.0.abs() < MAX_OPENING_SCORE {
+            break pos;
+        }
+    };
+
+    let mut records: Vec<(Board, i16)> = Vec::new();
+    let mut win_adj: Option<(Color, u16)> = None;
+    let mut draw_plies = 0u16;
+    let mut ply = 0u16;
+
+    //White-relative: 0 = Loss, 1 = Draw, 2 = Win
+    let result = loop {
+        let board = pos.board().clone();
+        let stm_result = |win: bool| match (board.stm(), win) {
+            (Color::White, true) | (Color::Black, false) => 2u8,
+            _ => 0u8,
+        };
+
+        match board.status() {
+            BoardStatus::Checkmate => break stm_result(false),
+            BoardStatus::Draw => break 1,
+            BoardStatus::Ongoing => {}
+        }
+
+        if pos.is_draw() {
+            break 1;
+        }
+
+        //WDL tables are only exact right after a zeroing move and without castling rights
+        if board.halfmove_clock() == 0
+            && board.castle_rights(Color::White).is_none()
+            && board.castle_rights(Color::Black).is_none()
+            && let Some(wdl) = probe_wdl(&board)
+        {
+            match wdl {
+                WdlProbeResult::Win => break stm_result(true),
+                WdlProbeResult::Loss => break stm_result(false),
+                WdlProbeResult::CursedWin | WdlProbeResult::BlessedLoss | WdlProbeResult::Draw =>
+                    break 1,
+            }
+        }
+
+        let (mv, score) = search(searcher, &pos, options.nodes, engine_options);
+
+        //the same side has to be winning for every ply, not just some side
+        if score.0.abs() >= WIN_ADJ_SCORE {
+            let winner = if score > Score::ZERO {
+                board.stm()
+            } else {
+                !board.stm()
+            };
+            win_adj = match win_adj {
+                Some((color, plies)) if color == winner => Some((color, plies + 1)),
+                _ => Some((winner, 1)),
+            };
+        } else {
+            win_adj = None;
+        }
+
+        if ply >= DRAW_ADJ_MIN_PLY && score.0.abs() <= DRAW_ADJ_SCORE {
+            draw_plies += 1;
+        } else {
+            draw_plies = 0;
+        }
+
+        if let Some((winner, plies)) = win_adj
+            && plies >= WIN_ADJ_PLIES
+        {
+            break stm_result(winner == board.stm());
+        }
+
+        if draw_plies >= DRAW_ADJ_PLIES {
+            break 1;
+        }
+
+        if !board.in_check() && !mv.is_noisy() && !score.is_decisive() {
+            records.push((board, score.0 as i16));
+        }
+
+        pos.make_move(mv);
+        pos.reset_nnue();
+        ply += 1;
+    };
+
+    records
+        .into_iter()
+        .map(|(board, score)| {
+            let result = match board.stm() {
+                Color::White => result,
+                Color::Black => 2 - result,
+            };
+
+            PackedBoard::new(&board, score, result)
+        })
+        .collect()
+}
+
+/*----------------------------------------------------------------*/
+
 impl Engine {
     #[inline]
     pub fn gen_fens(&mut self, num: usize, seed: u64, dfrc: bool, moves: usize) {
@@ -44,7 +318,7 @@ impl Engine {
                         self.pos.clone(),
                         vec![SearchLimit::Nodes(1000)],
                         self.options,
-                        SearchInfo::None,
+                        SearchInfo::none(),
                     );
                     self.searcher.wait();
 
@@ -52,7 +326,107 @@ impl Engine {
                 });
             }
 
-            println!("info string genfens {}", opening.unwrap().to_fen(dfrc));
+            let fen = opening.unwrap().to_fen(dfrc);
+            if self.options.json {
+                println!("{}", JsonObject::new("genfens").str("fen", &fen));
+            } else {
+                println!("info string genfens {fen}");
+            }
+        }
+    }
+}
+
+impl Engine {
+    pub fn datagen(&mut self, options: DatagenOptions) {
+        let file = match File::create(&options.output) {
+            Ok(file) => file,
+            Err(e) => {
+                self.message(format!("Failed to create `{}`: {e}", options.output));
+                return;
+            }
+        };
+
+        let mut writer = BufWriter::new(file);
+        let next_game = AtomicUsize::new(0);
+        let (tx, rx) = mpsc::channel::<(usize, Vec<PackedBoard>)>();
+
+        let result = std::thread::scope(|s| {
+            for _ in 0..options.threads {
+                let tx = tx.clone();
+                let next_game = &next_game;
+                let options = &options;
+
+                s.spawn(move || {
+                    let mut searcher = Searcher::default();
+
+                    loop {
+                        let game = next_game.fetch_add(1, Ordering::Relaxed);
+                        if game >= options.games {
+                            break;
+                        }
+
+                        //Every game gets its own RNG so the output doesn't depend on scheduling
+                        let mut rng = SmallRng::seed_from_u64(
+                            options.seed ^ (game as u64).wrapping_mul(0x9E3779B97F4A7C15),
+                        );
+                        let records = play_game(&mut searcher, &mut rng, options);
+
+                        if tx.send((game, records)).is_err() {
+                            break;
+                        }
+                    }
+
+                    searcher.quit();
+                });
+            }
+
+            drop(tx);
+
+            let mut pending = BTreeMap::new();
+            let mut next_write = 0;
+            let mut positions = 0;
+
+            for (game, records) in rx {
+                pending.insert(game, records);
+
+                while let Some(records) = pending.remove(&next_write) {
+                    let written = records
+                        .iter()
+                        .try_for_each(|record| writer.write_all(&record.to_bytes()));
+
+                    if let Err(e) = written {
+                        //Stop handing out games, dropping the receiver makes pending sends fail
+                        next_game.store(options.games, Ordering::Relaxed);
+                        return Err(e);
+                    }
+
+                    positions += records.len();
+                    next_write += 1;
+
+                    if next_write % 100 == 0 || next_write == options.games {
+                        if self.options.json {
+                            let mut output = JsonObject::new("datagen");
+                            output
+                                .num("games", next_write)
+                                .num("total", options.games)
+                                .num("positions", positions);
+
+                            println!("{output}");
+                        } else {
+                            println!(
+                                "info string datagen games {next_write}/{} positions {positions}",
+                                options.games
+                            );
+                        }
+                    }
+                }
+            }
+
+            writer.flush()
+        });
+
+        if let Err(e) = result {
+            self.message(format!("Failed to write `{}`: {e}", options.output));
         }
     }
 }
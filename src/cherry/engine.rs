@@ -1,4 +1,6 @@
-use std::time::*;
+use std::{fs, time::*};
+
+use colored::Colorize;
 
 use crate::*;
 
@@ -6,26 +8,14 @@ use crate::*;
 
 pub const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-fn perft<const BULK: bool>(board: &Board, depth: u8) -> u64 {
-    if depth == 0 {
-        return 1;
-    }
-
-    let mut nodes = 0;
-    let move_list = board.gen_moves();
-
-    if BULK && depth == 1 {
-        nodes += move_list.len() as u64;
+#[inline]
+fn nps(nodes: u64, elapsed: Duration) -> u128 {
+    let nanos = elapsed.as_nanos();
+    if nanos > 0 {
+        (nodes as u128 * 1_000_000_000) / nanos
     } else {
-        for &mv in move_list.iter() {
-            let mut board = board.clone();
-            board.make_move(mv);
-
-            nodes += perft::<BULK>(&board, depth - 1);
-        }
+        0
     }
-
-    nodes
 }
 
 /*----------------------------------------------------------------*/
@@ -42,11 +32,19 @@ pub struct EngineOptions {
     pub minimal: bool,
     pub eval_scaling: bool,
     pub score_normalisation: bool,
+    pub syzygy_probe_depth: u8,
+    pub syzygy_probe_limit: u8,
     pub move_overhead: u64,
     pub soft_target: bool,
     pub ponder: bool,
+    pub skill_level: u8,
+    pub skill_seed: u64,
     pub wdl: bool,
     pub frc: bool,
+    pub json: bool,
+    pub own_book: bool,
+    pub book_depth: u16,
+    pub book_variety: u8,
 }
 
 impl Default for EngineOptions {
@@ -57,19 +55,44 @@ impl Default for EngineOptions {
             minimal: false,
             eval_scaling: true,
             score_normalisation: true,
+            syzygy_probe_depth: 1,
+            syzygy_probe_limit: 7,
             move_overhead: DEFAULT_OVERHEAD,
             soft_target: false,
             ponder: false,
+            skill_level: MAX_SKILL_LEVEL,
+            skill_seed: 0,
             wdl: true,
             frc: false,
+            json: false,
+            own_book: false,
+            book_depth: DEFAULT_BOOK_DEPTH,
+            book_variety: DEFAULT_BOOK_VARIETY,
         }
     }
 }
 
+impl EngineOptions {
+    #[inline]
+    pub fn skill(&self) -> Option<Skill> {
+        Skill::from_level(self.skill_level)
+    }
+}
+
 pub struct Engine {
     pub pos: Position,
     pub searcher: Searcher,
     pub options: EngineOptions,
+    pub protocol: Protocol,
+    pub xboard: XboardState,
+    pub book: Option<Book>,
+}
+
+impl Default for Engine {
+    #[inline]
+    fn default() -> Self {
+        Engine::new()
+    }
 }
 
 impl Engine {
@@ -79,15 +102,49 @@ impl Engine {
             pos: Position::new(Board::startpos()),
             searcher: Searcher::default(),
             options: EngineOptions::default(),
+            protocol: Protocol::Uci,
+            xboard: XboardState::default(),
+            book: None,
+        }
+    }
+
+    //Prints an `info string`, or a `message` object in JSON mode
+    #[inline]
+    pub fn message(&self, text: impl std::fmt::Display) {
+        if self.options.json {
+            println!(
+                "{}",
+                JsonObject::new("message").str("text", &text.to_string())
+            );
+        } else {
+            println!("info string {text}");
         }
     }
 
     #[inline]
     pub fn handle(&mut self, input: &str) -> Abort {
+        //a GUI may answer `bestmove` before the searcher is idle again
+        if self.searcher.is_stopping() {
+            self.searcher.wait();
+        }
+
+        if self.protocol == Protocol::Uci
+            && matches!(
+                input.split_ascii_whitespace().next(),
+                Some("xboard" | "protover")
+            )
+        {
+            self.protocol = Protocol::Xboard;
+        }
+
+        if self.protocol == Protocol::Xboard {
+            return self.handle_xboard(input);
+        }
+
         let cmd = match UciCommand::parse(input, self.pos.board(), self.options.frc) {
             Ok(cmd) => cmd,
             Err(e) => {
-                println!("info string {e}");
+                self.message(e);
                 return Abort::No;
             }
         };
@@ -102,17 +159,41 @@ impl Engine {
             UciCommand::Go(limits) => self.go(limits),
             UciCommand::Perft { depth, bulk } => self.perft(depth, bulk),
             UciCommand::SplitPerft { depth, bulk } => self.splitperft(depth, bulk),
-            UciCommand::SetOption { name, value } => self.set_option(name, value),
+            UciCommand::PerftSuite(path) => self.perft_suite(&path),
+            UciCommand::Book => self.book(),
+            UciCommand::MakeBook { pgn, output, plies } => self.make_book(&pgn, &output, plies),
+            UciCommand::SaveHash(path) => self.save_hash(&path),
+            UciCommand::LoadHash(path) => self.load_hash(&path),
+            UciCommand::SaveWeights(path) => self.save_weights(&path),
+            UciCommand::LoadWeights(path) => self.load_weights(&path),
+            UciCommand::FitWdl { path, apply } => self.fit_wdl(&path, apply),
+            UciCommand::Analyze {
+                pgn,
+                limits,
+                output,
+            } => self.analyze(&pgn, limits, &output),
+            UciCommand::SetOption { name, value } => match self.set_option(&name, &value) {
+                Ok((name, OptionValue::Button)) => self.message(format!("Pressed {name}")),
+                Ok((name, value)) => self.message(format!("Set {name} to {value}")),
+                Err(e) => self.message(e),
+            },
             UciCommand::Bench { depth } => self.bench(depth),
+            UciCommand::Epd { path, limits } => self.epd(&path, limits),
             UciCommand::GenFens {
                 num,
                 seed,
                 dfrc,
                 moves,
             } => self.gen_fens(num, seed, dfrc, moves),
+            UciCommand::Datagen(options) => self.datagen(options),
+            UciCommand::Match(options) => self.run_match(options),
             #[cfg(feature = "tune")]
             UciCommand::Spsa => W::print_spsa(),
+            #[cfg(feature = "tune")]
+            UciCommand::SpsaTune(options) => self.spsa(options),
+            UciCommand::RootMoves => self.root_moves(),
             UciCommand::Wait => self.wait(),
+            UciCommand::PonderHit => self.ponderhit(),
             UciCommand::Stop => self.stop(),
             UciCommand::Quit => return self.quit(),
         }
@@ -124,20 +205,9 @@ impl Engine {
     fn uci(&self) {
         println!("id name Cherry v{ENGINE_VERSION}-dev");
         println!("id author Tecci");
-        println!("option name Threads type spin default 1 min 1 max {MAX_THREADS}");
-        println!("option name Hash type spin default 16 min 1 max {MAX_TT_SIZE}");
-        println!("option name MultiPV type spin default 1 min 1 max 218");
-        println!("option name Minimal type check default false");
-        println!("option name EvalScaling type check default true");
-        println!("option name ScoreNormalisation type check default true");
-        println!("option name SyzygyPath type string default <empty>");
-        println!("option name MoveOverhead type spin default {DEFAULT_OVERHEAD} min 0 max 5000");
-        println!("option name SoftTarget type check default false");
-        println!("option name Ponder type check default false");
-        println!("option name UCI_ShowWDL type check default true");
-        println!("option name UCI_Chess960 type check default false");
-        #[cfg(feature = "tune")]
-        W::print_uci();
+        for option in OPTIONS.iter() {
+            println!("{option}");
+        }
         println!("uciok");
     }
 
@@ -152,6 +222,18 @@ impl Engine {
         let (w, l) = wdl_model(scaled_eval, material);
         let d = 1000 - w - l;
 
+        if self.options.json {
+            let mut output = JsonObject::new("eval");
+            output
+                .object("raw", &json_score(raw_eval))
+                .object("scaled", &json_score(scaled_eval))
+                .object("normalised", &json_score(normalised_eval))
+                .num_array("wdl", [w, d, l]);
+
+            println!("{output}");
+            return;
+        }
+
         let to_pct = |x: i16| x as f32 / 10.0;
         let w_pct = to_pct(w);
         let d_pct = to_pct(d);
@@ -170,11 +252,23 @@ impl Engine {
 
     #[inline]
     fn display(&self) {
+        if self.options.json {
+            let board = self.pos.board();
+            let mut output = JsonObject::new("board");
+            output
+                .str("fen", &board.to_fen(self.options.frc))
+                .str("hash", &format!("{:#016X}", board.hash()))
+                .str("stm", &format!("{:?}", board.stm()));
+
+            println!("{output}");
+            return;
+        }
+
         println!("{}", self.pos.board().print(self.options.frc));
     }
 
     #[inline]
-    fn set_position(&mut self, board: Board, moves: Vec<Move>) {
+    pub fn set_position(&mut self, board: Board, moves: Vec<Move>) {
         self.pos.set_board(board);
         for mv in moves {
             self.pos.make_move(mv);
@@ -182,42 +276,124 @@ impl Engine {
         }
     }
 
+    /// Starts searching the current position in the background, reporting through `info`.
+    #[inline]
+    pub fn search(&mut self, limits: Vec<SearchLimit>, info: SearchInfo) {
+        self.searcher
+            .search(self.pos.clone(), limits, self.options, info);
+    }
+
+    /// Stops the current search, if any, and waits for it to finish.
+    #[inline]
+    pub fn stop_search(&self) {
+        if self.searcher.is_searching() {
+            self.searcher.stop();
+        }
+
+        self.searcher.wait();
+    }
+
+    #[inline]
+    pub fn wait_search(&self) {
+        self.searcher.wait();
+    }
+
+    #[inline]
+    pub fn best_move(&self) -> Option<Move> {
+        self.searcher.shared.best_move()
+    }
+
+    #[inline]
+    pub fn best_score(&self) -> Score {
+        self.searcher.shared.best_score()
+    }
+
     #[inline]
     fn go(&mut self, limits: Vec<SearchLimit>) {
         if self.searcher.is_searching() {
-            println!("info string Already Searching");
+            self.message("Already Searching");
             return;
         }
 
-        self.searcher.search(
-            self.pos.clone(),
-            limits,
-            self.options,
-            SearchInfo::Uci {
+        //analysis, pondering, restricted and fixed depth or node searches never use the book
+        let use_book = !SearchLimit::is_analysis(&limits)
+            && !limits.iter().any(|l| {
+                matches!(
+                    l,
+                    SearchLimit::Ponder
+                        | SearchLimit::SearchMoves(_)
+                        | SearchLimit::Mate(_)
+                        | SearchLimit::Depth(_)
+                        | SearchLimit::Nodes(_)
+                )
+            });
+        if use_book && let Some(mv) = self.book_move() {
+            self.reporter().best_move(self.pos.board(), mv, None);
+            return;
+        }
+
+        self.search(limits, self.reporter());
+    }
+
+    #[inline]
+    fn reporter(&self) -> SearchInfo {
+        if self.options.json {
+            SearchInfo::new(JsonReporter {
+                normalisation: self.options.score_normalisation,
+                wdl: self.options.wdl,
+                frc: self.options.frc,
+            })
+        } else {
+            SearchInfo::new(UciReporter {
                 minimal: self.options.minimal,
                 normalisation: self.options.score_normalisation,
                 wdl: self.options.wdl,
                 frc: self.options.frc,
-            },
-        );
+            })
+        }
+    }
+
+    #[inline]
+    fn root_moves(&self) {
+        if self.searcher.is_searching() {
+            self.message("Not Allowed to report root moves while Searching");
+            return;
+        }
+
+        match self.searcher.shared.root_report.lock().unwrap().as_ref() {
+            Some((board, depth, moves)) => self.reporter().root_moves(board, *depth, moves),
+            None => self.message("No search to report root moves of"),
+        }
     }
 
     #[inline]
     fn perft(&mut self, depth: u8, bulk: bool) {
         let board = self.pos.board().clone();
+        let table = PerftTable::new(DEFAULT_PERFT_HASH);
         let time = Instant::now();
-        let nodes = if bulk {
-            perft::<true>(&board, depth)
+        let nodes = if depth == 0 {
+            1
         } else {
-            perft::<false>(&board, depth)
+            board
+                .split_perft(depth, bulk, self.searcher.num_threads(), &table)
+                .iter()
+                .map(|&(_, nodes)| nodes)
+                .sum()
         };
         let elapsed = time.elapsed();
-        let nanos = elapsed.as_nanos();
-        let nps = if nanos > 0 {
-            (nodes as u128 * 1_000_000_000) / nanos
-        } else {
-            0
-        };
+        let nps = nps(nodes, elapsed);
+
+        if self.options.json {
+            let mut output = JsonObject::new("perft");
+            output
+                .num("depth", depth)
+                .num("nodes", nodes)
+                .num("time", elapsed.as_millis())
+                .num("nps", nps);
+
+            println!("{output}");
+            return;
+        }
 
         println!("nodes {nodes} time {elapsed:.2?} nps {nps}");
     }
@@ -229,221 +405,296 @@ impl Engine {
         }
 
         let board = self.pos.board().clone();
-        let mut perft_data = Vec::new();
-        let mut total_time = Duration::ZERO;
-        let mut total_nodes = 0u64;
-
-        for &mv in board.gen_moves().iter() {
-            let mut board = board.clone();
-            board.make_move(mv);
-
-            let time = Instant::now();
-            let nodes = if bulk {
-                perft::<true>(&board, depth)
-            } else {
-                perft::<false>(&board, depth)
-            };
+        let table = PerftTable::new(DEFAULT_PERFT_HASH);
+        let time = Instant::now();
+        let perft_data = board.split_perft(
+            depth.saturating_add(1),
+            bulk,
+            self.searcher.num_threads(),
+            &table,
+        );
+        let total_time = time.elapsed();
+        let total_nodes = perft_data.iter().map(|&(_, nodes)| nodes).sum::<u64>();
+        let nps = nps(total_nodes, total_time);
+
+        if self.options.json {
+            for (mv, nodes) in perft_data {
+                let mut output = JsonObject::new("splitperft");
+                output
+                    .str("move", &mv.display(&board, self.options.frc).to_string())
+                    .num("nodes", nodes);
+
+                println!("{output}");
+            }
 
-            total_time += time.elapsed();
-            total_nodes += nodes;
+            let mut output = JsonObject::new("perft");
+            output
+                .num("depth", depth)
+                .num("nodes", total_nodes)
+                .num("time", total_time.as_millis())
+                .num("nps", nps);
 
-            perft_data.push((mv, nodes));
+            println!("{output}");
+            return;
         }
 
         for (mv, nodes) in perft_data {
             println!("{:<5}: {nodes}", mv.display(&board, self.options.frc));
         }
 
-        let nanos = total_time.as_nanos();
-        let nps = if nanos > 0 {
-            (total_nodes as u128 * 1_000_000_000) / nanos
-        } else {
-            0
-        };
-
         println!("\nnodes {total_nodes} time {total_time:.2?} nps {nps}");
     }
 
-    #[inline]
-    fn set_option(&mut self, name: String, value: String) {
-        match name.as_str() {
-            "Threads" => {
-                if self.searcher.is_searching() {
-                    println!("info string Not Allowed to set Threads while Searching");
-                    return;
-                }
+    fn perft_suite(&mut self, path: &str) {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                self.message(format!("Failed to read `{path}`: {e}"));
+                return;
+            }
+        };
 
-                let value = match value.parse::<u32>() {
-                    Ok(value) => value,
-                    Err(e) => {
-                        println!("info string {:?}", UciParseError::InvalidInteger(e));
-                        return;
-                    }
-                };
-
-                if value == 0 || value > MAX_THREADS {
-                    println!("info string Invalid Number of Threads: `{value}`");
-                    return;
-                }
+        let threads = self.searcher.num_threads();
+        let table = PerftTable::new(DEFAULT_PERFT_HASH);
+        let mut total = 0;
+        let mut passed = 0;
+        let mut total_nodes = 0;
+        let time = Instant::now();
 
-                self.searcher.set_threads(value);
-                println!("info string Set Threads to {value}");
+        for (line_num, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
             }
-            "Hash" => {
-                if self.searcher.is_searching() {
-                    println!("info string Not Allowed to set Hash while Searching");
-                    return;
-                }
 
-                let value = match value.parse::<u64>() {
-                    Ok(value) => value,
-                    Err(e) => {
-                        println!("info string {:?}", UciParseError::InvalidInteger(e));
-                        return;
-                    }
-                };
-
-                if value == 0 || value > MAX_TT_SIZE {
-                    println!("info string Invalid Hash Size: `{value}`");
-                    return;
-                }
+            let Some(entry) = PerftSuiteEntry::parse(line) else {
+                self.message(format!("Invalid perft EPD on line {}", line_num + 1));
+                continue;
+            };
 
-                self.searcher.resize_ttable(value);
-                println!("info string Set Hash to {value}");
-            }
-            "MultiPV" => {
-                let value = match value.parse::<u8>() {
-                    Ok(value) => value,
-                    Err(e) => {
-                        println!("info string {:?}", UciParseError::InvalidInteger(e));
-                        return;
-                    }
-                };
-
-                if value == 0 || value > 218 {
-                    println!("info string Invalid MultiPV value: `{value}`");
-                    return;
+            for &(depth, expected) in &entry.counts {
+                let time = Instant::now();
+                let nodes = entry
+                    .board
+                    .split_perft(depth, true, threads, &table)
+                    .iter()
+                    .map(|&(_, nodes)| nodes)
+                    .sum::<u64>();
+                let elapsed = time.elapsed();
+                let is_passed = nodes == expected;
+
+                total += 1;
+                total_nodes += nodes;
+                if is_passed {
+                    passed += 1;
                 }
 
-                self.options.multipv = value;
-                println!("info string Set MultiPV to {value}");
-            }
-            "Minimal" => {
-                let value = match value.parse::<bool>() {
-                    Ok(value) => value,
-                    Err(e) => {
-                        println!("info string {:?}", UciParseError::InvalidBoolean(e));
-                        return;
-                    }
-                };
-
-                self.options.minimal = value;
-                println!("info string Set Minimal to {value}");
-            }
-            "EvalScaling" => {
-                let value = match value.parse::<bool>() {
-                    Ok(value) => value,
-                    Err(e) => {
-                        println!("info string {:?}", UciParseError::InvalidBoolean(e));
-                        return;
-                    }
-                };
-
-                self.options.eval_scaling = value;
-                println!("info string Set EvalScaling to {value}");
-            }
-            "ScoreNormalisation" => {
-                let value = match value.parse::<bool>() {
-                    Ok(value) => value,
-                    Err(e) => {
-                        println!("info string {:?}", UciParseError::InvalidBoolean(e));
-                        return;
-                    }
-                };
-
-                self.options.score_normalisation = value;
-                println!("info string Set ScoreNormalisation to {value}");
-            }
-            "SyzygyPath" => set_syzygy_path(value.as_str()),
-            "MoveOverhead" => {
-                let value = match value.parse::<u64>() {
-                    Ok(value) => value,
-                    Err(e) => {
-                        println!("info string {:?}", UciParseError::InvalidInteger(e));
-                        return;
-                    }
-                };
-
-                if value > 5000 {
-                    println!("info string Invalid MoveOverhead value: `{value}`");
-                    return;
+                if self.options.json {
+                    let mut output = JsonObject::new("perftsuite");
+                    output
+                        .str("fen", &entry.board.to_fen(true))
+                        .num("depth", depth)
+                        .num("expected", expected)
+                        .num("nodes", nodes)
+                        .bool("passed", is_passed)
+                        .num("time", elapsed.as_millis());
+
+                    println!("{output}");
+                    continue;
                 }
 
-                self.options.move_overhead = value;
-                println!("info string Set MoveOverhead to {value}");
-            }
-            "SoftTarget" => {
-                let value = match value.parse::<bool>() {
-                    Ok(value) => value,
-                    Err(e) => {
-                        println!("info string {:?}", UciParseError::InvalidBoolean(e));
-                        return;
-                    }
-                };
-
-                self.options.soft_target = value;
-                println!("info string Set SoftTarget to {value}");
-            }
-            "Ponder" => {
-                let value = match value.parse::<bool>() {
-                    Ok(value) => value,
-                    Err(e) => {
-                        println!("info string {:?}", UciParseError::InvalidBoolean(e));
-                        return;
-                    }
-                };
-
-                self.options.ponder = value;
-                println!("info string Set Ponder to {value}");
+                println!(
+                    "{:>4} {:<6} line {:<4} depth {:<2} nodes {:<12} expected {:<12} time {:>6}ms",
+                    total,
+                    if is_passed { "passed" } else { "failed" },
+                    line_num + 1,
+                    depth,
+                    nodes,
+                    expected,
+                    elapsed.as_millis(),
+                );
             }
-            "UCI_ShowWDL" => {
-                let value = match value.parse::<bool>() {
-                    Ok(value) => value,
-                    Err(e) => {
-                        println!("info string {:?}", UciParseError::InvalidBoolean(e));
-                        return;
-                    }
-                };
-
-                self.options.wdl = value;
-                println!("info string Set UCI_ShowWDL to {value}");
-            }
-            "UCI_Chess960" => {
-                let value = match value.parse::<bool>() {
-                    Ok(value) => value,
-                    Err(e) => {
-                        println!("info string {:?}", UciParseError::InvalidBoolean(e));
-                        return;
-                    }
-                };
-
-                self.options.frc = value;
-                println!("info string Set UCI_Chess960 to {value}");
+        }
+
+        let elapsed = time.elapsed();
+        let nps = nps(total_nodes, elapsed);
+        if self.options.json {
+            let mut output = JsonObject::new("perftsuite_summary");
+            output
+                .num("passed", passed)
+                .num("total", total)
+                .num("nodes", total_nodes)
+                .num("time", elapsed.as_millis())
+                .num("nps", nps);
+
+            println!("{output}");
+            return;
+        }
+
+        println!("passed {passed}/{total} nodes {total_nodes} time {elapsed:.2?} nps {nps}");
+    }
+
+    /// Sets an option from the registry, returning its name and parsed value.
+    pub fn set_option(
+        &mut self,
+        name: &str,
+        value: &str,
+    ) -> Result<(&'static str, OptionValue), OptionError> {
+        let option = OPTIONS
+            .get(name)
+            .ok_or_else(|| OptionError::UnknownOption(name.to_string()))?;
+        let value = option.parse(value)?;
+        (option.callback)(self, &value)?;
+
+        Ok((option.name, value))
+    }
+
+    /// Picks a move from the opening book, if `OwnBook` is enabled and the position is in it.
+    pub fn book_move(&self) -> Option<Move> {
+        let board = self.pos.board();
+        let ply = 2 * (board.fullmove_count() - 1) + (board.stm() == Color::Black) as u16;
+        if !self.options.own_book || ply >= self.options.book_depth {
+            return None;
+        }
+
+        self.book
+            .as_ref()?
+            .pick(board, self.options.book_variety, &mut rand::rng())
+    }
+
+    #[inline]
+    fn book(&self) {
+        let Some(book) = &self.book else {
+            self.message("No Book Loaded");
+            return;
+        };
+
+        let board = self.pos.board();
+        let moves = book.moves(board);
+        let total = moves.iter().map(|&(_, weight)| weight as u64).sum::<u64>();
+        let to_pct = |weight: u16| {
+            if total > 0 {
+                weight as f32 * 100.0 / total as f32
+            } else {
+                0.0
             }
-            #[cfg(feature = "tune")]
-            name if W::is_weight(name) => W::set_weight(name, value),
-            _ => println!("info string Unknown Option: `{name}`"),
+        };
+
+        if self.options.json {
+            let entries = moves.iter().map(|&(mv, weight)| {
+                let mut object = JsonObject::empty();
+                object
+                    .str("move", &mv.display(board, self.options.frc).to_string())
+                    .num("weight", weight)
+                    .num("percent", format!("{:.2}", to_pct(weight)));
+
+                object
+            });
+
+            let mut output = JsonObject::new("book");
+            output
+                .str("key", &format!("{:016X}", board.polyglot_hash()))
+                .object_array("moves", entries);
+
+            println!("{output}");
+            return;
+        }
+
+        println!("key {:016X}", board.polyglot_hash());
+        for (mv, weight) in moves {
+            println!(
+                "{:<5}: weight {weight} ({:.2}%)",
+                mv.display(board, self.options.frc),
+                to_pct(weight)
+            );
+        }
+    }
+
+    #[inline]
+    fn make_book(&self, pgn: &str, output: &str, plies: usize) {
+        let mut builder = BookBuilder::new(plies);
+        let result = fs::read_to_string(pgn)
+            .map_err(BookError::from)
+            .and_then(|pgn| builder.add_pgn(&pgn));
+        if let Err(e) = result {
+            self.message(e);
+            return;
+        }
+
+        let games = builder.games();
+        let book = builder.build();
+        match book.save(output) {
+            Ok(()) => self.message(format!(
+                "Wrote {} entries from {games} games to `{output}`",
+                book.len()
+            )),
+            Err(e) => self.message(e),
+        }
+    }
+
+    #[inline]
+    fn save_hash(&self, path: &str) {
+        if self.searcher.is_searching() {
+            self.message("Not Allowed to save Hash while Searching");
+            return;
+        }
+
+        match self.searcher.save_hash(path) {
+            Ok(()) => self.message(format!("Saved Hash to `{path}`")),
+            Err(e) => self.message(e),
+        }
+    }
+
+    #[inline]
+    fn load_hash(&self, path: &str) {
+        if self.searcher.is_searching() {
+            self.message("Not Allowed to load Hash while Searching");
+            return;
+        }
+
+        match self.searcher.load_hash(path) {
+            Ok(()) => self.message(format!("Loaded Hash from `{path}`")),
+            Err(e) => self.message(e),
+        }
+    }
+
+    #[inline]
+    fn save_weights(&self, path: &str) {
+        match W::save(path) {
+            Ok(()) => self.message(format!("Saved Weights to `{path}`")),
+            Err(e) => self.message(e),
+        }
+    }
+
+    #[inline]
+    fn load_weights(&self, path: &str) {
+        if self.searcher.is_searching() {
+            self.message("Not Allowed to load Weights while Searching");
+            return;
+        }
+
+        match W::load(path) {
+            Ok(count) => self.message(format!("Loaded {count} Weights from `{path}`")),
+            Err(e) => self.message(e),
         }
     }
 
     #[inline]
     fn wait(&self) {
         if !self.searcher.is_searching() {
-            println!("info string Not Searching");
+            self.message("Not Searching");
         } else {
-            println!("info string Waiting for Search to Stop...");
+            self.message("Waiting for Search to Stop...");
             self.searcher.wait();
-            println!("info string Searcher Stopped");
+            self.message("Searcher Stopped");
+        }
+    }
+
+    #[inline]
+    fn ponderhit(&self) {
+        if self.searcher.is_searching() {
+            self.searcher.ponderhit();
+        } else {
+            self.message("Not Searching");
         }
     }
 
@@ -453,9 +704,9 @@ impl Engine {
             self.searcher.stop();
             self.searcher.wait();
 
-            println!("info string Searcher Stopped");
+            self.message("Searcher Stopped");
         } else {
-            println!("info string Not Searching");
+            self.message("Not Searching");
         }
     }
 
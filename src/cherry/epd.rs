@@ -0,0 +1,354 @@
+use std::{fs, sync::mpsc, time::*};
+
+use crate::*;
+
+/*----------------------------------------------------------------*/
+
+#[derive(Debug, Clone)]
+pub struct EpdEntry {
+    pub board: Board,
+    pub id: Option<String>,
+    pub best_moves: Vec<Move>,
+    pub avoid_moves: Vec<Move>,
+    pub points: Vec<(Move, u32)>,
+}
+
+impl EpdEntry {
+    pub fn parse(line: &str) -> Option<EpdEntry> {
+        let mut fields = line.trim().splitn(5, char::is_whitespace);
+        let placement = fields.next()?;
+        let stm = fields.next()?;
+        let castling = fields.next()?;
+        let ep = fields.next()?;
+
+        //Lines copied from a full FEN still carry the move clocks before the operations
+        let mut ops = fields.next().unwrap_or("").trim_start();
+        let mut clocks = Vec::new();
+        while clocks.len() < 2 {
+            let (token, rest) = ops.split_once(char::is_whitespace).unwrap_or((ops, ""));
+            if token.parse::<u16>().is_err() {
+                break;
+            }
+
+            clocks.push(token);
+            ops = rest.trim_start();
+        }
+
+        let ops = split_ops(ops)?;
+        let operand = |opcode: &str| {
+            ops.iter()
+                .find(|(op, _)| *op == opcode)
+                .map(|(_, operand)| operand.as_str())
+        };
+        let hmvc = operand("hmvc").or(clocks.first().copied()).unwrap_or("0");
+        let fmvn = operand("fmvn").or(clocks.get(1).copied()).unwrap_or("1");
+        let board =
+            Board::from_fen(&format!("{placement} {stm} {castling} {ep} {hmvc} {fmvn}")).ok()?;
+
+        let parse_moves = |opcode: &str| {
+            operand(opcode).map_or(Some(Vec::new()), |moves| {
+                moves
+                    .split_whitespace()
+                    .map(|san| Move::parse_san(&board, san))
+                    .collect::<Option<Vec<_>>>()
+            })
+        };
+        let best_moves = parse_moves("bm")?;
+        let avoid_moves = parse_moves("am")?;
+
+        //STS suites store the points for each move in `c0`, e.g. `c0 "Qe2=10, Rd1=5"`
+        let points = operand("c0")
+            .map(|c0| {
+                c0.split(',')
+                    .filter_map(|entry| {
+                        let (san, points) = entry.trim().rsplit_once('=')?;
+                        Some((Move::parse_san(&board, san)?, points.parse::<u32>().ok()?))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        Some(EpdEntry {
+            id: operand("id").map(String::from),
+            board,
+            best_moves,
+            avoid_moves,
+            points,
+        })
+    }
+
+    #[inline]
+    pub fn is_solved(&self, mv: Move) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.contains(&mv))
+            && !self.avoid_moves.contains(&mv)
+    }
+
+    #[inline]
+    pub fn max_points(&self) -> u32 {
+        self.points.iter().map(|&(_, p)| p).max().unwrap_or(0)
+    }
+
+    #[inline]
+    pub fn points(&self, mv: Move) -> u32 {
+        self.points
+            .iter()
+            .find(|&&(m, _)| m == mv)
+            .map_or(0, |&(_, p)| p)
+    }
+}
+
+fn split_ops(ops: &str) -> Option<Vec<(String, String)>> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    let mut in_string = false;
+
+    for c in ops.chars() {
+        match c {
+            '"' => in_string = !in_string,
+            ';' if !in_string => {
+                if !current.trim().is_empty() {
+                    result.push(split_op(&current)?);
+                }
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+
+        current.push(c);
+    }
+
+    if !current.trim().is_empty() {
+        result.push(split_op(&current)?);
+    }
+
+    Some(result)
+}
+
+fn split_op(op: &str) -> Option<(String, String)> {
+    let op = op.trim();
+    let (opcode, operand) = op.split_once(char::is_whitespace).unwrap_or((op, ""));
+
+    //opcodes start with a letter, anything else is a stray field
+    if !opcode.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let operand = operand.trim();
+    let operand = operand
+        .strip_prefix('"')
+        .and_then(|o| o.strip_suffix('"'))
+        .unwrap_or(operand);
+
+    Some((opcode.to_string(), operand.to_string()))
+}
+
+/*----------------------------------------------------------------*/
+
+impl Engine {
+    pub fn epd(&mut self, path: &str, limits: Vec<SearchLimit>) {
+        if self.searcher.is_searching() {
+            self.message("Not Allowed to run EPD while Searching");
+            return;
+        }
+
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                self.message(format!("Failed to read `{path}`: {e}"));
+                return;
+            }
+        };
+
+        let options = EngineOptions {
+            multipv: 1,
+            skill_level: MAX_SKILL_LEVEL,
+            ponder: false,
+            ..self.options
+        };
+
+        let mut total = 0;
+        let mut solved = 0;
+        let mut points = 0;
+        let mut max_points = 0;
+        let mut total_time = Duration::ZERO;
+        let mut solve_time = Duration::ZERO;
+
+        for (line_num, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let Some(entry) = EpdEntry::parse(line) else {
+                self.message(format!("Invalid EPD on line {}", line_num + 1));
+                continue;
+            };
+
+            self.searcher.newgame();
+
+            let (sender, receiver) = mpsc::channel();
+            let time = Instant::now();
+            self.searcher.search(
+                Position::new(entry.board.clone()),
+                limits.clone(),
+                options,
+                SearchInfo::new(sender),
+            );
+            self.searcher.wait();
+            let elapsed = time.elapsed();
+
+            //the solution counts from the first report after which the best move stayed correct
+            let mut solved_at = None;
+            for event in receiver.try_iter() {
+                if let SearchEvent::Info(report) = event
+                    && report.pv_index == 0
+                    && let Some(&pv_move) = report.pv.first()
+                {
+                    if entry.is_solved(pv_move) {
+                        solved_at.get_or_insert(report.time);
+                    } else {
+                        solved_at = None;
+                    }
+                }
+            }
+
+            let mv = self.searcher.shared.best_move();
+            let is_solved = mv.is_some_and(|mv| entry.is_solved(mv));
+            let found = match solved_at {
+                Some(solved_at) if is_solved => solved_at.min(elapsed),
+                _ => elapsed,
+            };
+
+            total += 1;
+            total_time += elapsed;
+            if is_solved {
+                solved += 1;
+                solve_time += found;
+            }
+
+            let san = |mv: &Move| mv.to_san(&entry.board);
+            let earned = mv.map_or(0, |mv| entry.points(mv));
+            if !entry.points.is_empty() {
+                points += earned;
+                max_points += entry.max_points();
+            }
+
+            if self.options.json {
+                let mut output = JsonObject::new("epd");
+                output
+                    .num("index", total)
+                    .str("id", entry.id.as_deref().unwrap_or("-"))
+                    .bool("solved", is_solved)
+                    .num("time", found.as_millis())
+                    .str_array("bm", entry.best_moves.iter().map(san))
+                    .str_array("am", entry.avoid_moves.iter().map(san));
+                match mv {
+                    Some(mv) => output.str("move", &san(&mv)),
+                    None => output.null("move"),
+                };
+                if !entry.points.is_empty() {
+                    output
+                        .num("points", earned)
+                        .num("max_points", entry.max_points());
+                }
+
+                println!("{output}");
+                continue;
+            }
+
+            let mut output = format!(
+                "{:>4} {:<24} {:<6} {:<8} time {:>6}ms",
+                total,
+                entry.id.as_deref().unwrap_or("-"),
+                if is_solved { "solved" } else { "failed" },
+                mv.as_ref().map_or(String::from("-"), san),
+                found.as_millis(),
+            );
+
+            for (name, moves) in [("bm", &entry.best_moves), ("am", &entry.avoid_moves)] {
+                if !moves.is_empty() {
+                    output.push_str(&format!(" {name}"));
+                    for mv in moves {
+                        output.push_str(&format!(" {}", san(mv)));
+                    }
+                }
+            }
+
+            if !entry.points.is_empty() {
+                output.push_str(&format!(" points {}/{}", earned, entry.max_points()));
+            }
+
+            println!("{output}");
+        }
+
+        let avg_solve_time = solve_time.as_millis() / (solved as u128).max(1);
+        if self.options.json {
+            let mut output = JsonObject::new("epd_summary");
+            output
+                .num("solved", solved)
+                .num("total", total)
+                .num("time", total_time.as_millis())
+                .num("avg_solve_time", avg_solve_time);
+            if max_points > 0 {
+                output.num("points", points).num("max_points", max_points);
+            }
+
+            println!("{output}");
+            return;
+        }
+
+        println!("solved {solved}/{total} time {total_time:.2?} avg solve time {avg_solve_time}ms");
+        if max_points > 0 {
+            println!("points {points}/{max_points}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STARTPOS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+
+    #[test]
+    fn epd_parse_operations() {
+        let entry = EpdEntry::parse(&format!(
+            "{STARTPOS} bm e4 d4; am a3; id \"start; pos\"; c0 \"e4=10, d4=8\"; hmvc 4;"
+        ))
+        .unwrap();
+        let mv = |san: &str| Move::parse_san(&entry.board, san).unwrap();
+
+        assert_eq!(entry.id.as_deref(), Some("start; pos"));
+        assert_eq!(entry.best_moves, vec![mv("e4"), mv("d4")]);
+        assert_eq!(entry.avoid_moves, vec![mv("a3")]);
+        assert_eq!(entry.points, vec![(mv("e4"), 10), (mv("d4"), 8)]);
+        assert_eq!(entry.board.halfmove_clock(), 4);
+        assert_eq!(entry.board.fullmove_count(), 1);
+        assert!(entry.is_solved(mv("d4")));
+        assert!(!entry.is_solved(mv("Nf3")));
+    }
+
+    #[test]
+    fn epd_parse_full_fen() {
+        let entry = EpdEntry::parse(&format!("{STARTPOS} 0 1 bm e4;")).unwrap();
+        let e4 = Move::parse_san(&entry.board, "e4").unwrap();
+        assert_eq!(entry.best_moves, vec![e4]);
+        assert_eq!(entry.board.fullmove_count(), 1);
+
+        let entry = EpdEntry::parse(&format!("{STARTPOS} 3 20 am a3; id \"clocks\";")).unwrap();
+        assert_eq!(entry.id.as_deref(), Some("clocks"));
+        assert_eq!(entry.avoid_moves.len(), 1);
+        assert_eq!(entry.board.halfmove_clock(), 3);
+        assert_eq!(entry.board.fullmove_count(), 20);
+
+        let entry = EpdEntry::parse(&format!("{STARTPOS} 0 1")).unwrap();
+        assert!(entry.best_moves.is_empty());
+    }
+
+    #[test]
+    fn epd_parse_errors() {
+        assert!(EpdEntry::parse(&format!("{STARTPOS} bm e4; 5 d4;")).is_none());
+        assert!(EpdEntry::parse(&format!("{STARTPOS} bm e5;")).is_none());
+        assert!(EpdEntry::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w").is_none());
+    }
+}
@@ -0,0 +1,173 @@
+use core::fmt::{self, Write};
+
+use crate::*;
+
+/*----------------------------------------------------------------*/
+
+/*
+Minimal builder for single-line JSON objects,
+used by the JSON-lines output mode.
+*/
+#[derive(Debug, Clone)]
+pub struct JsonObject {
+    buffer: String,
+    empty: bool,
+}
+
+impl JsonObject {
+    #[inline]
+    pub fn empty() -> JsonObject {
+        JsonObject {
+            buffer: String::from("{"),
+            empty: true,
+        }
+    }
+
+    #[inline]
+    pub fn new(event: &str) -> JsonObject {
+        let mut object = JsonObject::empty();
+        object.str("type", event);
+        object
+    }
+
+    #[inline]
+    fn key(&mut self, key: &str) {
+        if !self.empty {
+            self.buffer.push(',');
+        }
+
+        self.empty = false;
+        write_escaped(&mut self.buffer, key);
+        self.buffer.push(':');
+    }
+
+    #[inline]
+    pub fn str(&mut self, key: &str, value: &str) -> &mut JsonObject {
+        self.key(key);
+        write_escaped(&mut self.buffer, value);
+        self
+    }
+
+    #[inline]
+    pub fn num(&mut self, key: &str, value: impl fmt::Display) -> &mut JsonObject {
+        self.key(key);
+        write!(self.buffer, "{value}").unwrap();
+        self
+    }
+
+    #[inline]
+    pub fn bool(&mut self, key: &str, value: bool) -> &mut JsonObject {
+        self.num(key, value)
+    }
+
+    #[inline]
+    pub fn null(&mut self, key: &str) -> &mut JsonObject {
+        self.num(key, "null")
+    }
+
+    #[inline]
+    pub fn object(&mut self, key: &str, value: &JsonObject) -> &mut JsonObject {
+        self.num(key, value)
+    }
+
+    pub fn object_array(
+        &mut self,
+        key: &str,
+        values: impl IntoIterator<Item = JsonObject>,
+    ) -> &mut JsonObject {
+        self.num_array(key, values)
+    }
+
+    pub fn str_array<T: fmt::Display>(
+        &mut self,
+        key: &str,
+        values: impl IntoIterator<Item = T>,
+    ) -> &mut JsonObject {
+        self.key(key);
+        self.buffer.push('[');
+        for (i, value) in values.into_iter().enumerate() {
+            if i > 0 {
+                self.buffer.push(',');
+            }
+
+            write_escaped(&mut self.buffer, &value.to_string());
+        }
+        self.buffer.push(']');
+        self
+    }
+
+    pub fn num_array<T: fmt::Display>(
+        &mut self,
+        key: &str,
+        values: impl IntoIterator<Item = T>,
+    ) -> &mut JsonObject {
+        self.key(key);
+        self.buffer.push('[');
+        for (i, value) in values.into_iter().enumerate() {
+            if i > 0 {
+                self.buffer.push(',');
+            }
+
+            write!(self.buffer, "{value}").unwrap();
+        }
+        self.buffer.push(']');
+        self
+    }
+}
+
+impl fmt::Display for JsonObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}}}", self.buffer)
+    }
+}
+
+fn write_escaped(buffer: &mut String, value: &str) {
+    buffer.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => buffer.push_str("\\\""),
+            '\\' => buffer.push_str("\\\\"),
+            '\n' => buffer.push_str("\\n"),
+            '\r' => buffer.push_str("\\r"),
+            '\t' => buffer.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(buffer, "\\u{:04x}", c as u32).unwrap(),
+            c => buffer.push(c),
+        }
+    }
+    buffer.push('"');
+}
+
+/*----------------------------------------------------------------*/
+
+#[inline]
+pub fn json_score(score: Score) -> JsonObject {
+    let mut object = JsonObject::empty();
+    match score.mate_in() {
+        Some(ply) => object.num("mate", (ply + ply.signum()) / 2),
+        None => object.num("cp", score.0),
+    };
+
+    object
+}
+
+#[inline]
+pub fn json_bound(bound: TTFlag) -> &'static str {
+    match bound {
+        TTFlag::UpperBound => "upper",
+        TTFlag::LowerBound => "lower",
+        _ => "exact",
+    }
+}
+
+#[inline]
+pub fn json_moves(board: &Board, moves: &[Move], frc: bool) -> Vec<String> {
+    let mut board = board.clone();
+    moves
+        .iter()
+        .map(|&mv| {
+            let out = mv.display(&board, frc).to_string();
+            board.make_move(mv);
+            out
+        })
+        .collect()
+}
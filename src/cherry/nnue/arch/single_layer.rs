@@ -136,15 +136,21 @@ pub struct Accumulator {
 
 impl Accumulator {
     #[inline]
-    pub fn extrapolate(&mut self, prev: &Accumulator, king: Square, perspective: Color) {
+    pub fn extrapolate(
+        &mut self,
+        prev: &Accumulator,
+        king: Square,
+        perspective: Color,
+        network: &NetworkWeights,
+    ) {
         let (add, sub) = (prev.update.add.unwrap(), prev.update.sub.unwrap());
-        let bucket = king_bucket(king, perspective);
+        let ft_weights = &network.ft_weights[king_bucket(king, perspective)];
 
         match (prev.update.add2, prev.update.sub2) {
             (Some(add2), Some(sub2)) => acc_add2_sub2(
+                ft_weights,
                 &prev.values[perspective],
                 &mut self.values[perspective],
-                bucket,
                 add.to_index(king, perspective),
                 add2.to_index(king, perspective),
                 sub.to_index(king, perspective),
@@ -152,17 +158,17 @@ impl Accumulator {
             ),
             (Some(_), None) => unreachable!(),
             (None, Some(sub2)) => acc_add_sub2(
+                ft_weights,
                 &prev.values[perspective],
                 &mut self.values[perspective],
-                bucket,
                 add.to_index(king, perspective),
                 sub.to_index(king, perspective),
                 sub2.to_index(king, perspective),
             ),
             (None, None) => acc_add_sub(
+                ft_weights,
                 &prev.values[perspective],
                 &mut self.values[perspective],
-                bucket,
                 add.to_index(king, perspective),
                 sub.to_index(king, perspective),
             ),
@@ -174,7 +180,13 @@ impl Accumulator {
     /*----------------------------------------------------------------*/
 
     #[inline]
-    pub fn reset(&mut self, board: &Board, cache: &mut AccumulatorCache, perspective: Color) {
+    pub fn reset(
+        &mut self,
+        board: &Board,
+        cache: &mut AccumulatorCache,
+        perspective: Color,
+        network: &NetworkWeights,
+    ) {
         let king = board.king(perspective);
         let mirror = HORIZONTAL_MIRRORING && should_mirror(king);
         let bucket = king_bucket(king, perspective);
@@ -196,7 +208,7 @@ impl Accumulator {
             }
         }
 
-        let ft_weights = &NETWORK.ft_weights[bucket];
+        let ft_weights = &network.ft_weights[bucket];
         let acc = &mut entry.values;
         for i in 0..(HL / 32) {
             let offset = i * 32;
@@ -231,8 +243,13 @@ impl Default for Accumulator {
 }
 
 #[inline]
-fn acc_add_sub(input: &[i16; HL], output: &mut [i16; HL], bucket: usize, add: usize, sub: usize) {
-    let ft_weights = &NETWORK.ft_weights[bucket];
+fn acc_add_sub(
+    ft_weights: &[i16; INPUT * HL],
+    input: &[i16; HL],
+    output: &mut [i16; HL],
+    add: usize,
+    sub: usize,
+) {
     for i in 0..(HL / 32) {
         let offset = i * 32;
 
@@ -248,14 +265,13 @@ fn acc_add_sub(input: &[i16; HL], output: &mut [i16; HL], bucket: usize, add: us
 
 #[inline]
 fn acc_add_sub2(
+    ft_weights: &[i16; INPUT * HL],
     input: &[i16; HL],
     output: &mut [i16; HL],
-    bucket: usize,
     add: usize,
     sub1: usize,
     sub2: usize,
 ) {
-    let ft_weights = &NETWORK.ft_weights[bucket];
     for i in 0..(HL / 32) {
         let offset = i * 32;
 
@@ -272,15 +288,14 @@ fn acc_add_sub2(
 
 #[inline]
 fn acc_add2_sub2(
+    ft_weights: &[i16; INPUT * HL],
     input: &[i16; HL],
     output: &mut [i16; HL],
-    bucket: usize,
     add1: usize,
     add2: usize,
     sub1: usize,
     sub2: usize,
 ) {
-    let ft_weights = &NETWORK.ft_weights[bucket];
     for i in 0..(HL / 32) {
         let offset = i * 32;
 
@@ -298,7 +313,7 @@ fn acc_add2_sub2(
 
 /*----------------------------------------------------------------*/
 
-#[derive(Debug, Copy, Clone, Default)]
+#[derive(Debug, Copy, Clone)]
 pub struct AccumulatorCache {
     pub entries: [[[AccumulatorCacheEntry; NUM_INPUT_BUCKETS]; 1 + HORIZONTAL_MIRRORING as usize];
         Color::COUNT],
@@ -310,12 +325,17 @@ pub struct AccumulatorCacheEntry {
     pub board: Byteboard,
 }
 
-impl Default for AccumulatorCacheEntry {
+impl AccumulatorCache {
     #[inline]
-    fn default() -> Self {
-        AccumulatorCacheEntry {
-            values: NETWORK.ft_bias,
+    pub fn new(network: &NetworkWeights) -> AccumulatorCache {
+        let entry = AccumulatorCacheEntry {
+            values: network.ft_bias,
             board: Byteboard(u8x64::splat(0)),
+        };
+
+        AccumulatorCache {
+            entries: [[[entry; NUM_INPUT_BUCKETS]; 1 + HORIZONTAL_MIRRORING as usize];
+                Color::COUNT],
         }
     }
 }
@@ -391,20 +411,26 @@ impl Nnue {
 
         let mut output = 0;
         if PAIRWISE_MUL {
-            feed_forward_pairwise(stm, ntm, bucket, &mut output);
+            feed_forward_pairwise(&self.network, stm, ntm, bucket, &mut output);
         } else {
-            feed_forward(stm, ntm, bucket, &mut output);
+            feed_forward(&self.network, stm, ntm, bucket, &mut output);
         }
 
-        (output / QA + i32::from(NETWORK.out_bias[bucket])) * EVAL_SCALE / (QA * QB)
+        (output / QA + i32::from(self.network.out_bias[bucket])) * EVAL_SCALE / (QA * QB)
     }
 }
 
 /*----------------------------------------------------------------*/
 
 #[inline]
-fn feed_forward(stm: &[i16; HL], ntm: &[i16; HL], bucket: usize, output: &mut i32) {
-    let out_weights = &NETWORK.out_weights[bucket];
+fn feed_forward(
+    network: &NetworkWeights,
+    stm: &[i16; HL],
+    ntm: &[i16; HL],
+    bucket: usize,
+    output: &mut i32,
+) {
+    let out_weights = &network.out_weights[bucket];
     let (zero, qa) = (i16x32::splat(0), i16x32::splat(QA as i16));
     let mut sum = i32x16::splat(0);
 
@@ -426,8 +452,14 @@ fn feed_forward(stm: &[i16; HL], ntm: &[i16; HL], bucket: usize, output: &mut i3
 }
 
 #[inline]
-fn feed_forward_pairwise(stm: &[i16; HL], ntm: &[i16; HL], bucket: usize, output: &mut i32) {
-    let out_weights = &NETWORK.out_weights[bucket];
+fn feed_forward_pairwise(
+    network: &NetworkWeights,
+    stm: &[i16; HL],
+    ntm: &[i16; HL],
+    bucket: usize,
+    output: &mut i32,
+) {
+    let out_weights = &network.out_weights[bucket];
     let (zero, qa) = (i16x32::splat(0), i16x32::splat(QA as i16));
     let mut sum = i32x16::splat(0);
 
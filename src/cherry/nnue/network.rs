@@ -1,24 +1,95 @@
+use core::ops::Deref;
+use std::{
+    fs,
+    io,
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
 use crate::*;
 
 pub static NETWORK: NetworkWeights =
     unsafe { std::mem::transmute(*include_bytes!(concat!(env!("OUT_DIR"), "/network.nnue"))) };
 
+static LOADED_NETWORK: RwLock<Option<Arc<NetworkWeights>>> = RwLock::new(None);
+
+/*
+Handle to the network a position evaluates with, taken when the position is created or reloaded.
+Loading another network only affects new handles, so searches that are
+still running keep the network they started with alive.
+*/
+#[derive(Debug, Clone)]
+pub struct NetworkHandle(Option<Arc<NetworkWeights>>);
+
+impl NetworkHandle {
+    #[inline]
+    pub fn current() -> NetworkHandle {
+        NetworkHandle(LOADED_NETWORK.read().unwrap().clone())
+    }
+}
+
+impl Deref for NetworkHandle {
+    type Target = NetworkWeights;
+
+    #[inline]
+    fn deref(&self) -> &NetworkWeights {
+        self.0.as_deref().unwrap_or(&NETWORK)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum NetworkLoadError {
+    #[error("Failed to read network file: `{0}`")]
+    Io(#[from] io::Error),
+    #[error("Invalid network size: expected {expected} bytes, found {found} bytes")]
+    InvalidSize { expected: usize, found: usize },
+}
+
+pub fn load_network<P: AsRef<Path>>(path: P) -> Result<(), NetworkLoadError> {
+    let bytes = fs::read(path)?;
+    let expected = size_of::<NetworkWeights>();
+
+    if bytes.len() != expected {
+        return Err(NetworkLoadError::InvalidSize {
+            expected,
+            found: bytes.len(),
+        });
+    }
+
+    let weights = unsafe {
+        let mut weights = Box::<NetworkWeights>::new_zeroed();
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), weights.as_mut_ptr().cast::<u8>(), expected);
+        weights.assume_init()
+    };
+
+    *LOADED_NETWORK.write().unwrap() = Some(Arc::from(weights));
+    Ok(())
+}
+
+#[inline]
+pub fn unload_network() {
+    *LOADED_NETWORK.write().unwrap() = None;
+}
+
 #[derive(Debug, Clone)]
 pub struct Nnue {
     pub acc_stack: Box<[Accumulator; MAX_PLY as usize + 1]>,
     pub acc_cache: AccumulatorCache,
     pub acc_index: usize,
+    pub network: NetworkHandle,
 }
 
 impl Nnue {
     pub fn new(board: &Board) -> Nnue {
+        let network = NetworkHandle::current();
         let mut nnue = Nnue {
             acc_stack: vec![Accumulator::default(); MAX_PLY as usize + 1]
                 .into_boxed_slice()
                 .try_into()
                 .unwrap(),
-            acc_cache: AccumulatorCache::default(),
+            acc_cache: AccumulatorCache::new(&network),
             acc_index: 0,
+            network,
         };
 
         nnue.full_reset(board);
@@ -32,9 +103,21 @@ impl Nnue {
         self.reset(board, Color::Black);
     }
 
+    #[inline]
+    pub fn reload(&mut self, board: &Board) {
+        self.network = NetworkHandle::current();
+        self.acc_cache = AccumulatorCache::new(&self.network);
+        self.full_reset(board);
+    }
+
     #[inline]
     pub fn reset(&mut self, board: &Board, perspective: Color) {
-        self.acc_stack[self.acc_index].reset(board, &mut self.acc_cache, perspective);
+        self.acc_stack[self.acc_index].reset(
+            board,
+            &mut self.acc_cache,
+            perspective,
+            &self.network,
+        );
     }
 
     #[inline]
@@ -58,7 +141,7 @@ impl Nnue {
         let king = board.king(perspective);
         for index in clean_index..self.acc_index {
             let [clean, dirty] = self.acc_stack.get_disjoint_mut([index, index + 1]).unwrap();
-            dirty.extrapolate(clean, king, perspective);
+            dirty.extrapolate(clean, king, perspective, &self.network);
         }
     }
 
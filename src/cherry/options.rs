@@ -0,0 +1,417 @@
+use core::fmt;
+use std::sync::LazyLock;
+
+use crate::*;
+
+/*----------------------------------------------------------------*/
+
+#[derive(Debug, Clone)]
+pub enum OptionKind {
+    Spin {
+        default: i64,
+        min: i64,
+        max: i64,
+    },
+    Check {
+        default: bool,
+    },
+    String {
+        default: &'static str,
+    },
+    Button,
+    Combo {
+        default: &'static str,
+        vars: &'static [&'static str],
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum OptionValue {
+    Spin(i64),
+    Check(bool),
+    String(String),
+    Button,
+}
+
+impl OptionValue {
+    #[inline]
+    pub fn spin(&self) -> i64 {
+        match self {
+            OptionValue::Spin(value) => *value,
+            _ => unreachable!(),
+        }
+    }
+
+    #[inline]
+    pub fn check(&self) -> bool {
+        match self {
+            OptionValue::Check(value) => *value,
+            _ => unreachable!(),
+        }
+    }
+
+    #[inline]
+    pub fn string(&self) -> &str {
+        match self {
+            OptionValue::String(value) => value,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl fmt::Display for OptionValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptionValue::Spin(value) => write!(f, "{value}"),
+            OptionValue::Check(value) => write!(f, "{value}"),
+            OptionValue::String(value) => write!(f, "{value}"),
+            OptionValue::Button => Ok(()),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum OptionError {
+    #[error("Unknown Option: `{0}`")]
+    UnknownOption(String),
+    #[error("Not Allowed to set {0} while Searching")]
+    Searching(&'static str),
+    #[error("Error parsing integer: `{0}`")]
+    InvalidInteger(String),
+    #[error("Error parsing boolean: `{0}`")]
+    InvalidBoolean(String),
+    #[error("Invalid {0} value: `{1}`")]
+    InvalidValue(&'static str, String),
+    #[error("{0}")]
+    Failed(String),
+    #[error("{0} is shared by all players and can't be set per player")]
+    Global(&'static str),
+}
+
+/*----------------------------------------------------------------*/
+
+pub type OptionCallback = fn(&mut Engine, &OptionValue) -> Result<(), OptionError>;
+
+pub struct UciOption {
+    pub name: &'static str,
+    pub kind: OptionKind,
+    pub callback: OptionCallback,
+}
+
+impl UciOption {
+    pub fn parse(&self, value: &str) -> Result<OptionValue, OptionError> {
+        match &self.kind {
+            OptionKind::Spin { min, max, .. } => {
+                let value = value
+                    .parse::<i64>()
+                    .map_err(|_| OptionError::InvalidInteger(value.to_string()))?;
+
+                if value < *min || value > *max {
+                    return Err(OptionError::InvalidValue(self.name, value.to_string()));
+                }
+
+                Ok(OptionValue::Spin(value))
+            }
+            OptionKind::Check { .. } => value
+                .parse::<bool>()
+                .map(OptionValue::Check)
+                .map_err(|_| OptionError::InvalidBoolean(value.to_string())),
+            OptionKind::String { .. } => Ok(OptionValue::String(value.to_string())),
+            OptionKind::Button => Ok(OptionValue::Button),
+            OptionKind::Combo { vars, .. } => vars
+                .iter()
+                .find(|var| var.eq_ignore_ascii_case(value))
+                .map(|var| OptionValue::String(var.to_string()))
+                .ok_or_else(|| OptionError::InvalidValue(self.name, value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for UciOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "option name {} type ", self.name)?;
+
+        match &self.kind {
+            OptionKind::Spin { default, min, max } => {
+                write!(f, "spin default {default} min {min} max {max}")
+            }
+            OptionKind::Check { default } => write!(f, "check default {default}"),
+            OptionKind::String { default } => write!(f, "string default {default}"),
+            OptionKind::Button => write!(f, "button"),
+            OptionKind::Combo { default, vars } => {
+                write!(f, "combo default {default}")?;
+                for var in vars.iter() {
+                    write!(f, " var {var}")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+pub struct OptionRegistry {
+    options: Vec<UciOption>,
+}
+
+impl OptionRegistry {
+    #[inline]
+    pub fn add(&mut self, name: &'static str, kind: OptionKind, callback: OptionCallback) {
+        self.options.push(UciOption {
+            name,
+            kind,
+            callback,
+        });
+    }
+
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&UciOption> {
+        self.options
+            .iter()
+            .find(|option| option.name.eq_ignore_ascii_case(name))
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &UciOption> {
+        self.options.iter()
+    }
+}
+
+pub static OPTIONS: LazyLock<OptionRegistry> = LazyLock::new(|| {
+    use OptionKind::*;
+
+    let mut options = OptionRegistry {
+        options: Vec::new(),
+    };
+
+    options.add(
+        "Threads",
+        Spin {
+            default: 1,
+            min: 1,
+            max: MAX_THREADS as i64,
+        },
+        |engine, value| {
+            if engine.searcher.is_searching() {
+                return Err(OptionError::Searching("Threads"));
+            }
+
+            engine.searcher.set_threads(value.spin() as u32);
+            Ok(())
+        },
+    );
+    options.add(
+        "Hash",
+        Spin {
+            default: 16,
+            min: 1,
+            max: MAX_TT_SIZE as i64,
+        },
+        |engine, value| {
+            if engine.searcher.is_searching() {
+                return Err(OptionError::Searching("Hash"));
+            }
+
+            engine.searcher.resize_ttable(value.spin() as u64);
+            Ok(())
+        },
+    );
+    options.add("Clear Hash", Button, |engine, _| {
+        if engine.searcher.is_searching() {
+            return Err(OptionError::Searching("Clear Hash"));
+        }
+
+        engine.searcher.newgame();
+        Ok(())
+    });
+    options.add(
+        "MultiPV",
+        Spin {
+            default: 1,
+            min: 1,
+            max: 218,
+        },
+        |engine, value| {
+            engine.options.multipv = value.spin() as u8;
+            Ok(())
+        },
+    );
+    options.add("Minimal", Check { default: false }, |engine, value| {
+        engine.options.minimal = value.check();
+        Ok(())
+    });
+    options.add("EvalScaling", Check { default: true }, |engine, value| {
+        engine.options.eval_scaling = value.check();
+        Ok(())
+    });
+    options.add(
+        "ScoreNormalisation",
+        Check { default: true },
+        |engine, value| {
+            engine.options.score_normalisation = value.check();
+            Ok(())
+        },
+    );
+    options.add("SyzygyPath", String { default: "<empty>" }, |_, value| {
+        set_syzygy_path(value.string());
+
+        if value.string() != "<empty>" && !is_syzygy_enabled() {
+            return Err(OptionError::Failed(format!(
+                "Failed to load Syzygy tablebases from `{}`",
+                value.string()
+            )));
+        }
+
+        Ok(())
+    });
+    options.add(
+        "SyzygyProbeDepth",
+        Spin {
+            default: 1,
+            min: 1,
+            max: MAX_DEPTH as i64,
+        },
+        |engine, value| {
+            engine.options.syzygy_probe_depth = value.spin() as u8;
+            Ok(())
+        },
+    );
+    options.add(
+        "SyzygyProbeLimit",
+        Spin {
+            default: 7,
+            min: 0,
+            max: 7,
+        },
+        |engine, value| {
+            engine.options.syzygy_probe_limit = value.spin() as u8;
+            Ok(())
+        },
+    );
+    options.add(
+        "EvalFile",
+        String {
+            default: "<embedded>",
+        },
+        |engine, value| {
+            if engine.searcher.is_searching() {
+                return Err(OptionError::Searching("EvalFile"));
+            }
+
+            if value.string() == "<embedded>" {
+                unload_network();
+            } else {
+                load_network(value.string()).map_err(|e| OptionError::Failed(e.to_string()))?;
+            }
+
+            engine.pos.reload_nnue();
+            Ok(())
+        },
+    );
+    options.add(
+        "MoveOverhead",
+        Spin {
+            default: DEFAULT_OVERHEAD as i64,
+            min: 0,
+            max: 5000,
+        },
+        |engine, value| {
+            engine.options.move_overhead = value.spin() as u64;
+            Ok(())
+        },
+    );
+    options.add("SoftTarget", Check { default: false }, |engine, value| {
+        engine.options.soft_target = value.check();
+        Ok(())
+    });
+    options.add("Ponder", Check { default: false }, |engine, value| {
+        engine.options.ponder = value.check();
+        Ok(())
+    });
+    options.add(
+        "Skill Level",
+        Spin {
+            default: MAX_SKILL_LEVEL as i64,
+            min: 0,
+            max: MAX_SKILL_LEVEL as i64,
+        },
+        |engine, value| {
+            engine.options.skill_level = value.spin() as u8;
+            Ok(())
+        },
+    );
+    options.add(
+        "SkillSeed",
+        Spin {
+            default: 0,
+            min: 0,
+            max: u32::MAX as i64,
+        },
+        |engine, value| {
+            engine.options.skill_seed = value.spin() as u64;
+            Ok(())
+        },
+    );
+    options.add("UCI_ShowWDL", Check { default: true }, |engine, value| {
+        engine.options.wdl = value.check();
+        Ok(())
+    });
+    options.add("UCI_Chess960", Check { default: false }, |engine, value| {
+        engine.options.frc = value.check();
+        Ok(())
+    });
+    options.add("OwnBook", Check { default: false }, |engine, value| {
+        engine.options.own_book = value.check();
+        Ok(())
+    });
+    options.add(
+        "BookFile",
+        String { default: "<empty>" },
+        |engine, value| {
+            if value.string() == "<empty>" {
+                engine.book = None;
+                return Ok(());
+            }
+
+            let book =
+                Book::load(value.string()).map_err(|e| OptionError::Failed(e.to_string()))?;
+            engine.book = Some(book);
+            Ok(())
+        },
+    );
+    options.add(
+        "BookDepth",
+        Spin {
+            default: DEFAULT_BOOK_DEPTH as i64,
+            min: 0,
+            max: 1000,
+        },
+        |engine, value| {
+            engine.options.book_depth = value.spin() as u16;
+            Ok(())
+        },
+    );
+    options.add(
+        "BookVariety",
+        Spin {
+            default: DEFAULT_BOOK_VARIETY as i64,
+            min: 0,
+            max: 100,
+        },
+        |engine, value| {
+            engine.options.book_variety = value.spin() as u8;
+            Ok(())
+        },
+    );
+    options.add("JsonOutput", Check { default: false }, |engine, value| {
+        engine.options.json = value.check();
+        Ok(())
+    });
+
+    #[cfg(feature = "tune")]
+    W::register(&mut options);
+
+    options
+});
@@ -36,6 +36,11 @@ impl Position {
         self.nnue.full_reset(&self.current);
     }
 
+    #[inline]
+    pub fn reload_nnue(&mut self) {
+        self.nnue.reload(&self.current);
+    }
+
     /*----------------------------------------------------------------*/
 
     #[inline]
@@ -287,4 +292,114 @@ impl Position {
             .step_by(2)
             .any(|b| b.hash() == hash)
     }
+
+    /*
+    Upcoming Repetition Detection:
+    Checks if the side to move has a reversible move that leads
+    to a position that has already occurred, in which case it can at
+    least force a draw. Based on "Using cuckoo hashing to detect
+    upcoming repetitions" by Marcel van Kervinck.
+    */
+    pub fn has_game_cycle(&self, ply: u16) -> bool {
+        let since_null = self
+            .moves
+            .iter()
+            .rev()
+            .position(Option::is_none)
+            .unwrap_or(self.moves.len());
+        let end = (self.current.halfmove_clock() as usize).min(since_null);
+        if end < 3 {
+            return false;
+        }
+
+        let hash_at = |i: usize| self.boards[self.boards.len() - i].hash();
+        let hash = self.hash();
+        let occupied = self.current.occupied();
+        let mut other = hash ^ hash_at(1) ^ ZOBRIST.stm();
+
+        for i in (3..=end).step_by(2) {
+            other ^= hash_at(i - 1) ^ hash_at(i) ^ ZOBRIST.stm();
+            if other != 0 {
+                continue;
+            }
+
+            let Some(mv) = cuckoo_move(hash ^ hash_at(i)) else {
+                continue;
+            };
+
+            let (s1, s2) = (mv.src(), mv.dest());
+            if !(between(s1, s2) & occupied).is_empty() {
+                continue;
+            }
+
+            if ply as usize > i {
+                return true;
+            }
+
+            /*
+            Before the root, the move has to be ours, since both directions
+            of a move share a table entry (e.g. Rc1c5 and Rc5c1).
+            */
+            let sq = if occupied.has(s1) { s1 } else { s2 };
+            if self.current.color_on(sq) == Some(self.stm()) {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(fen: &str, moves: &str) -> Position {
+        let mut pos = Position::new(Board::from_fen(fen).unwrap());
+        for mv in moves.split_whitespace() {
+            pos.make_move(Move::parse(pos.board(), mv).unwrap());
+        }
+
+        pos
+    }
+
+    //The king walks a triangle back to e1 while the queen goes a8-b7-a6, so Qa8 repeats
+    const TRIANGLE: &str = "e1e2 a8b7 e2d2 b7a6 d2e1";
+
+    #[test]
+    fn game_cycle_forced() {
+        let pos = play("q3k3/8/8/8/8/8/8/4K3 w - - 0 1", TRIANGLE);
+
+        assert!(pos.has_game_cycle(0));
+        assert!(pos.has_game_cycle(6));
+        assert!(!play("q3k3/8/8/8/8/8/8/4K3 w - - 0 1", "e1e2 a8b7 e2d2").has_game_cycle(6));
+    }
+
+    #[test]
+    fn game_cycle_blocked() {
+        let pos = play("q3k3/p7/8/8/8/8/8/4K3 w - - 0 1", TRIANGLE);
+
+        assert!(!pos.has_game_cycle(0));
+        assert!(!pos.has_game_cycle(6));
+    }
+
+    #[test]
+    fn game_cycle_opponent_move() {
+        //Only black's Nc6-b8 would repeat the position, but it is white to move
+        let pos = play(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+            "g8f6 g1f3 b8c6 f3g1 f6g8",
+        );
+
+        assert!(!pos.has_game_cycle(0));
+        assert!(!pos.has_game_cycle(6));
+
+        //Once black has to move, the same knight move is a cycle before the root as well
+        let pos = play(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1",
+            "g1f3 b8c6 f3g1",
+        );
+
+        assert!(pos.has_game_cycle(0));
+    }
 }
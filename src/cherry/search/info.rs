@@ -1,19 +1,96 @@
-use std::fmt::Write;
+use std::{
+    fmt::Write,
+    sync::{
+        Arc,
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    time::Duration,
+};
 
 use crate::*;
 
+/*----------------------------------------------------------------*/
+
+#[derive(Debug, Clone)]
+pub struct SearchReport {
+    pub depth: u8,
+    pub sel_depth: u16,
+    pub multipv: u8,
+    pub pv_index: usize,
+    pub score: Score,
+    pub bound: TTFlag,
+    pub nodes: u64,
+    pub time: Duration,
+    pub nps: u128,
+    pub hashfull: u16,
+    pub pv: Vec<Move>,
+    pub last: bool,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct RootMoveReport {
+    pub mv: Move,
+    pub rank: usize,
+    pub nodes: u64,
+    pub score: Score,
+    pub bound: TTFlag,
+}
+
 #[derive(Debug, Clone)]
-pub enum SearchInfo {
-    Uci {
-        minimal: bool,
-        normalisation: bool,
-        wdl: bool,
-        frc: bool,
+pub enum SearchEvent {
+    Info(SearchReport),
+    CurrMove {
+        depth: u8,
+        mv: Move,
+        number: usize,
+    },
+    RootMoves {
+        depth: u8,
+        moves: Vec<RootMoveReport>,
+    },
+    NoMate(u16),
+    BestMove {
+        best_move: Move,
+        ponder_move: Option<Move>,
     },
-    None,
 }
 
+/*
+Receives the results of a search as it runs.
+`board` is always the root position of the search.
+`best_move` is always the last call, and is only made
+once the searcher is idle, so a new search can be started from it.
+*/
+pub trait SearchReporter: Send {
+    fn info(&mut self, board: &Board, report: &SearchReport);
+
+    fn curr_move(&mut self, _board: &Board, _depth: u8, _mv: Move, _number: usize) {}
+
+    fn root_moves(&mut self, _board: &Board, _depth: u8, _moves: &[RootMoveReport]) {}
+
+    fn no_mate(&mut self, _mate: u16) {}
+
+    fn best_move(&mut self, board: &Board, best_move: Move, ponder_move: Option<Move>);
+}
+
+/*----------------------------------------------------------------*/
+
+#[derive(Clone)]
+pub struct SearchInfo(Option<Arc<Mutex<dyn SearchReporter>>>);
+
 impl SearchInfo {
+    #[inline]
+    pub fn new(reporter: impl SearchReporter + 'static) -> SearchInfo {
+        SearchInfo(Some(Arc::new(Mutex::new(reporter))))
+    }
+
+    #[inline]
+    pub fn none() -> SearchInfo {
+        SearchInfo(None)
+    }
+
     pub fn update(
         &mut self,
         board: &Board,
@@ -27,99 +104,369 @@ impl SearchInfo {
         depth: u8,
         last: bool,
     ) {
-        match self {
-            SearchInfo::Uci {
-                minimal,
-                normalisation,
-                wdl,
-                frc,
-            } => {
-                if *minimal && !last {
-                    return;
-                }
+        let Some(reporter) = &self.0 else {
+            return;
+        };
 
-                let material = if *normalisation | *wdl {
-                    board.classic_material()
-                } else {
-                    0
-                };
+        if score.abs() <= 2 {
+            score = Score::ZERO;
+        }
 
-                let mut output = String::from("info ");
-                output.push_str(format!("depth {} seldepth {} ", depth, thread.sel_depth).as_str());
+        let nodes = thread.nodes.global();
+        let time = shared.time_man.elapsed();
+        let nps = if time.as_nanos() > 0 {
+            (nodes as u128 * 1_000_000_000) / time.as_nanos()
+        } else {
+            0
+        };
 
-                if multipv > 1 {
-                    output.push_str(format!("multipv {} ", pv_index + 1).as_str());
-                }
+        let report = SearchReport {
+            depth,
+            sel_depth: thread.sel_depth,
+            multipv,
+            pv_index,
+            score,
+            bound,
+            nodes,
+            time,
+            nps,
+            hashfull: shared.ttable.hash_usage(),
+            pv: pv.moves[..pv.len].iter().map_while(|&mv| mv).collect(),
+            last,
+        };
 
-                if score.abs() <= 2 {
-                    score = Score::ZERO;
-                }
+        reporter.lock().unwrap().info(board, &report);
+    }
+
+    #[inline]
+    pub fn curr_move(&mut self, board: &Board, depth: u8, mv: Move, number: usize) {
+        if let Some(reporter) = &self.0 {
+            reporter.lock().unwrap().curr_move(board, depth, mv, number);
+        }
+    }
+
+    #[inline]
+    pub fn root_moves(&mut self, board: &Board, depth: u8, moves: &[RootMoveReport]) {
+        if let Some(reporter) = &self.0 {
+            reporter.lock().unwrap().root_moves(board, depth, moves);
+        }
+    }
+
+    #[inline]
+    pub fn no_mate(&mut self, mate: u16) {
+        if let Some(reporter) = &self.0 {
+            reporter.lock().unwrap().no_mate(mate);
+        }
+    }
+
+    #[inline]
+    pub fn best_move(&mut self, board: &Board, best_move: Move, ponder_move: Option<Move>) {
+        if let Some(reporter) = &self.0 {
+            reporter
+                .lock()
+                .unwrap()
+                .best_move(board, best_move, ponder_move);
+        }
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+pub struct UciReporter {
+    pub minimal: bool,
+    pub normalisation: bool,
+    pub wdl: bool,
+    pub frc: bool,
+}
+
+impl SearchReporter for UciReporter {
+    fn info(&mut self, board: &Board, report: &SearchReport) {
+        if self.minimal && !report.last {
+            return;
+        }
+
+        let material = if self.normalisation | self.wdl {
+            board.classic_material()
+        } else {
+            0
+        };
+
+        let mut output = String::from("info ");
+        output.push_str(format!("depth {} seldepth {} ", report.depth, report.sel_depth).as_str());
+
+        if report.multipv > 1 {
+            output.push_str(format!("multipv {} ", report.pv_index + 1).as_str());
+        }
+
+        let out_score = if self.normalisation {
+            report.score.normalise(material)
+        } else {
+            report.score
+        };
+        output.push_str(format!("score {} ", out_score).as_str());
+        match report.bound {
+            TTFlag::Exact => {}
+            TTFlag::UpperBound => output.push_str("upperbound "),
+            TTFlag::LowerBound => output.push_str("lowerbound "),
+            TTFlag::None => {}
+        }
+
+        if self.wdl {
+            let (w, l) = wdl_model(report.score, material);
+            let d = 1000 - w - l;
+
+            output.push_str(format!("wdl {} {} {} ", w, d, l).as_str());
+        }
+
+        output.push_str(
+            format!(
+                "hashfull {} time {} nodes {} nps {} pv ",
+                report.hashfull,
+                report.time.as_millis(),
+                report.nodes,
+                report.nps,
+            )
+            .as_str(),
+        );
+
+        let mut board = board.clone();
+        for &mv in &report.pv {
+            write!(output, "{} ", mv.display(&board, self.frc)).unwrap();
+            board.make_move(mv);
+        }
+
+        println!("{output}");
+    }
+
+    #[inline]
+    fn curr_move(&mut self, board: &Board, depth: u8, mv: Move, number: usize) {
+        if !self.minimal {
+            println!(
+                "info depth {depth} currmove {} currmovenumber {number}",
+                mv.display(board, self.frc)
+            );
+        }
+    }
 
-                let out_score = if *normalisation {
-                    score.normalise(material)
+    fn root_moves(&mut self, board: &Board, depth: u8, moves: &[RootMoveReport]) {
+        let material = board.classic_material();
+
+        for entry in moves {
+            let mut output = format!(
+                "info string rootmove depth {depth} rank {} move {} nodes {}",
+                entry.rank,
+                entry.mv.display(board, self.frc),
+                entry.nodes,
+            );
+
+            if entry.bound != TTFlag::None {
+                let out_score = if self.normalisation {
+                    entry.score.normalise(material)
                 } else {
-                    score
+                    entry.score
                 };
-                output.push_str(format!("score {} ", out_score).as_str());
-                match bound {
-                    TTFlag::Exact => {}
-                    TTFlag::UpperBound => output.push_str("upperbound "),
-                    TTFlag::LowerBound => output.push_str("lowerbound "),
-                    TTFlag::None => {}
+
+                write!(output, " score {out_score}").unwrap();
+                match entry.bound {
+                    TTFlag::UpperBound => output.push_str(" upperbound"),
+                    TTFlag::LowerBound => output.push_str(" lowerbound"),
+                    _ => {}
                 }
+            }
+
+            println!("{output}");
+        }
+    }
 
-                if *wdl {
-                    let (w, l) = wdl_model(score, material);
-                    let d = 1000 - w - l;
+    #[inline]
+    fn no_mate(&mut self, mate: u16) {
+        println!("info string No mate in {mate} found");
+    }
 
-                    output.push_str(format!("wdl {} {} {} ", w, d, l).as_str());
-                }
+    fn best_move(&mut self, board: &Board, best_move: Move, ponder_move: Option<Move>) {
+        let mut output = String::new();
+
+        write!(output, "bestmove {}", best_move.display(board, self.frc)).unwrap();
+        if let Some(mv) = ponder_move {
+            write!(output, " ponder {}", mv.display(board, self.frc)).unwrap();
+        }
+
+        println!("{}", output);
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+pub struct XboardReporter {
+    pub post: bool,
+    pub normalisation: bool,
+    pub frc: bool,
+    pub report_move: Arc<AtomicBool>,
+}
+
+impl SearchReporter for XboardReporter {
+    fn info(&mut self, board: &Board, report: &SearchReport) {
+        if !self.post || report.last || report.bound != TTFlag::Exact {
+            return;
+        }
+
+        let mut output = format!(
+            "{} {} {} {}",
+            report.depth,
+            xboard_score(report.score, board, self.normalisation),
+            report.time.as_millis() / 10,
+            report.nodes,
+        );
+
+        let mut board = board.clone();
+        for &mv in &report.pv {
+            write!(output, " {}", xboard_move(&board, mv, self.frc)).unwrap();
+            board.make_move(mv);
+        }
+
+        println!("{output}");
+    }
+
+    fn best_move(&mut self, board: &Board, best_move: Move, _ponder_move: Option<Move>) {
+        if self.report_move.load(Ordering::Relaxed) {
+            println!("move {}", xboard_move(board, best_move, self.frc));
+        }
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+pub struct JsonReporter {
+    pub normalisation: bool,
+    pub wdl: bool,
+    pub frc: bool,
+}
+
+impl SearchReporter for JsonReporter {
+    fn info(&mut self, board: &Board, report: &SearchReport) {
+        let material = board.classic_material();
+        let out_score = if self.normalisation {
+            report.score.normalise(material)
+        } else {
+            report.score
+        };
+
+        let mut output = JsonObject::new("info");
+        output
+            .num("depth", report.depth)
+            .num("seldepth", report.sel_depth)
+            .num("multipv", report.pv_index + 1)
+            .object("score", &json_score(out_score));
+
+        output.str("bound", json_bound(report.bound));
+
+        if self.wdl {
+            let (w, l) = wdl_model(report.score, material);
+            output.num_array("wdl", [w, 1000 - w - l, l]);
+        }
 
-                let nodes = thread.nodes.global();
-                let time = shared.time_man.elapsed();
-                let nps = if time.as_nanos() > 0 {
-                    (nodes as u128 * 1_000_000_000) / time.as_nanos()
+        output
+            .num("nodes", report.nodes)
+            .num("nps", report.nps)
+            .num("time", report.time.as_millis())
+            .num("hashfull", report.hashfull)
+            .str_array("pv", json_moves(board, &report.pv, self.frc));
+
+        println!("{output}");
+    }
+
+    #[inline]
+    fn curr_move(&mut self, board: &Board, depth: u8, mv: Move, number: usize) {
+        let mut output = JsonObject::new("currmove");
+        output
+            .num("depth", depth)
+            .str("currmove", &mv.display(board, self.frc).to_string())
+            .num("currmovenumber", number);
+
+        println!("{output}");
+    }
+
+    fn root_moves(&mut self, board: &Board, depth: u8, moves: &[RootMoveReport]) {
+        let material = board.classic_material();
+        let entries = moves.iter().map(|entry| {
+            let mut object = JsonObject::empty();
+            object
+                .num("rank", entry.rank)
+                .str("move", &entry.mv.display(board, self.frc).to_string())
+                .num("nodes", entry.nodes);
+
+            if entry.bound == TTFlag::None {
+                object.null("score").null("bound");
+            } else {
+                let out_score = if self.normalisation {
+                    entry.score.normalise(material)
                 } else {
-                    0
+                    entry.score
                 };
 
-                output.push_str(
-                    format!(
-                        "hashfull {} time {} nodes {} nps {} pv {}",
-                        shared.ttable.hash_usage(),
-                        time.as_millis(),
-                        nodes,
-                        nps,
-                        pv.display(board, *frc)
-                    )
-                    .as_str(),
-                );
-
-                println!("{output}");
+                object
+                    .object("score", &json_score(out_score))
+                    .str("bound", json_bound(entry.bound));
             }
-            SearchInfo::None => {}
-        }
+
+            object
+        });
+
+        let mut output = JsonObject::new("rootmoves");
+        output.num("depth", depth).object_array("moves", entries);
+
+        println!("{output}");
     }
 
     #[inline]
-    pub fn best_move(&mut self, board: &Board, best_move: Move, ponder_move: Option<Move>) {
-        match self {
-            SearchInfo::Uci {
-                minimal: _minimal,
-                normalisation: _normalisation,
-                wdl: _wdl,
-                frc,
-            } => {
-                let mut output = String::new();
-
-                write!(output, "bestmove {}", best_move.display(board, *frc)).unwrap();
-                if let Some(mv) = ponder_move {
-                    write!(output, " ponder {}", mv.display(board, *frc)).unwrap();
-                }
+    fn no_mate(&mut self, mate: u16) {
+        println!("{}", JsonObject::new("nomate").num("mate", mate));
+    }
 
-                println!("{}", output);
-            }
-            SearchInfo::None => {}
-        }
+    fn best_move(&mut self, board: &Board, best_move: Move, ponder_move: Option<Move>) {
+        let mut output = JsonObject::new("bestmove");
+        output.str("bestmove", &best_move.display(board, self.frc).to_string());
+
+        match ponder_move {
+            Some(mv) => output.str("ponder", &mv.display(board, self.frc).to_string()),
+            None => output.null("ponder"),
+        };
+
+        println!("{output}");
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+impl SearchReporter for mpsc::Sender<SearchEvent> {
+    #[inline]
+    fn info(&mut self, _board: &Board, report: &SearchReport) {
+        let _ = self.send(SearchEvent::Info(report.clone()));
+    }
+
+    #[inline]
+    fn curr_move(&mut self, _board: &Board, depth: u8, mv: Move, number: usize) {
+        let _ = self.send(SearchEvent::CurrMove { depth, mv, number });
+    }
+
+    #[inline]
+    fn root_moves(&mut self, _board: &Board, depth: u8, moves: &[RootMoveReport]) {
+        let _ = self.send(SearchEvent::RootMoves {
+            depth,
+            moves: moves.to_vec(),
+        });
+    }
+
+    #[inline]
+    fn no_mate(&mut self, mate: u16) {
+        let _ = self.send(SearchEvent::NoMate(mate));
+    }
+
+    #[inline]
+    fn best_move(&mut self, _board: &Board, best_move: Move, ponder_move: Option<Move>) {
+        let _ = self.send(SearchEvent::BestMove {
+            best_move,
+            ponder_move,
+        });
     }
 }
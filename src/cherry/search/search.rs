@@ -1,5 +1,6 @@
 use std::sync::atomic::*;
 
+use pyrrhic_rs::WdlProbeResult;
 use smallvec::SmallVec;
 
 use crate::*;
@@ -11,6 +12,9 @@ pub const MAX_PLY: u16 = 256;
 pub const DEPTH_SCALE: i32 = 1024;
 pub const MAX_FRAC_DEPTH: i32 = MAX_DEPTH as i32 * DEPTH_SCALE;
 
+//milliseconds after which `currmove` info is sent
+pub const CURRMOVE_DELAY: u128 = 3000;
+
 /*----------------------------------------------------------------*/
 
 pub trait NodeType {
@@ -64,6 +68,45 @@ fn adjust_eval(eval: Score, corr: i32) -> Score {
 
 /*----------------------------------------------------------------*/
 
+/*
+Lists every root move of the thread, ranked with the
+current best move first, followed by the other moves
+by their last score (unsearched moves come last).
+*/
+pub fn root_move_report(thread: &ThreadData) -> Vec<RootMoveReport> {
+    let best_move = thread.root_pv.moves[0];
+    let mut moves = thread
+        .root_moves
+        .iter()
+        .map(|&mv| {
+            let (score, bound) = thread.root_scores[mv.src()][mv.dest()];
+
+            RootMoveReport {
+                mv,
+                rank: 0,
+                nodes: thread.root_nodes[mv.src()][mv.dest()],
+                score,
+                bound,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    moves.sort_by_key(|entry| {
+        (
+            Some(entry.mv) != best_move,
+            entry.bound == TTFlag::None,
+            -entry.score,
+            u64::MAX - entry.nodes,
+        )
+    });
+
+    for (i, entry) in moves.iter_mut().enumerate() {
+        entry.rank = i + 1;
+    }
+
+    moves
+}
+
 pub fn id_loop(
     mut pos: Position,
     thread: &mut ThreadData,
@@ -71,6 +114,7 @@ pub fn id_loop(
     mut info: SearchInfo,
 ) {
     thread.multipv = thread.multipv.min(thread.root_moves.len() as u8);
+    thread.report_multipv = thread.report_multipv.min(thread.multipv);
     thread
         .windows
         .extend((0..thread.multipv).map(|_| Window::new(W::asp_window_initial())));
@@ -85,9 +129,12 @@ pub fn id_loop(
     let mut score = -Score::INFINITE;
     let mut completed_depth = 0;
     let mut depth = 1;
+    let mut root_lines: Vec<(PrincipalVariation, Score)> = Vec::new();
+    let mut skill_lines = Vec::new();
 
     'id: loop {
         thread.exclude_moves.clear();
+        root_lines.clear();
 
         for pv_index in 0..thread.multipv as usize {
             thread.windows[pv_index].reset();
@@ -121,6 +168,7 @@ pub fn id_loop(
                     thread
                         .exclude_moves
                         .push(thread.search_stack[0].pv.moves[0].unwrap());
+                    root_lines.push((thread.search_stack[0].pv.clone(), new_score));
 
                     if pv_index == 0 {
                         thread.root_pv = thread.search_stack[0].pv.clone();
@@ -146,12 +194,12 @@ pub fn id_loop(
                         }
                     }
 
-                    if thread.id == 0 {
+                    if thread.id == 0 && pv_index < thread.report_multipv as usize {
                         info.update(
                             pos.board(),
                             &thread,
                             &shared,
-                            thread.multipv,
+                            thread.report_multipv,
                             pv_index,
                             &thread.search_stack[0].pv,
                             TTFlag::Exact,
@@ -172,12 +220,15 @@ pub fn id_loop(
                     (beta, TTFlag::LowerBound)
                 };
 
-                if thread.id == 0 && shared.time_man.elapsed().as_millis() >= 1000 {
+                if thread.id == 0
+                    && pv_index < thread.report_multipv as usize
+                    && shared.time_man.elapsed().as_millis() >= 1000
+                {
                     info.update(
                         pos.board(),
                         &thread,
                         &shared,
-                        thread.multipv,
+                        thread.report_multipv,
                         pv_index,
                         &thread.search_stack[0].pv,
                         bound,
@@ -193,9 +244,18 @@ pub fn id_loop(
 
         depth += 1;
         completed_depth += 1;
+        skill_lines.clone_from(&root_lines);
 
-        if thread.id == 0 && shared.time_man.stop_id(depth - 1, thread.nodes.global()) {
-            shared.time_man.set_stop(true);
+        if thread.id == 0 && thread.verbose {
+            info.root_moves(pos.board(), completed_depth, &root_move_report(thread));
+        }
+
+        if thread.id == 0
+            && shared
+                .time_man
+                .stop_id(depth - 1, thread.nodes.global(), score)
+        {
+            shared.time_man.abort();
             break 'id;
         }
 
@@ -218,29 +278,59 @@ pub fn id_loop(
         shared.time_man.wait_for_stop();
     }
 
+    if thread.id == 0 {
+        shared.time_man.set_stop(true);
+    }
+
     let last_thread = shared.num_searching.fetch_sub(1, Ordering::Relaxed) == 2;
     if last_thread && thread.id != 0 {
         atomic_wait::wake_all(&shared.num_searching);
     }
 
-    if thread.id == 0 {
-        if !last_thread {
-            let mut num_searching = shared.num_searching.load(Ordering::Relaxed);
-            while num_searching != 1 {
-                atomic_wait::wait(&shared.num_searching, num_searching);
-                num_searching = shared.num_searching.load(Ordering::Relaxed);
-            }
+    if thread.id == 0 && !last_thread {
+        let mut num_searching = shared.num_searching.load(Ordering::Relaxed);
+        while num_searching != 1 {
+            atomic_wait::wait(&shared.num_searching, num_searching);
+            num_searching = shared.num_searching.load(Ordering::Relaxed);
         }
+    }
 
-        shared.num_searching.store(0, Ordering::Relaxed);
+    if thread.id == 0
+        && let Some(skill) = thread.skill
+        && let Some(i) = skill.pick(
+            &skill_lines
+                .iter()
+                .map(|&(_, score)| score)
+                .collect::<Vec<_>>(),
+            pos.board(),
+            thread.skill_seed,
+        )
+    {
+        let (pv, pv_score) = skill_lines.swap_remove(i);
+        best_move = pv.moves[0];
+        ponder_move = pv.moves[1];
+        score = pv_score;
+        thread.root_pv = pv;
     }
 
     if thread.id == 0 {
+        //the results must be visible before anyone waiting on the search wakes up
+        shared.best_score.store(score.0, Ordering::Relaxed);
+        shared.best_move.store(
+            best_move.map(|mv| mv.bits()).unwrap_or(0),
+            Ordering::Relaxed,
+        );
+        *shared.root_report.lock().unwrap() = Some((
+            pos.board().clone(),
+            completed_depth,
+            root_move_report(thread),
+        ));
+
         info.update(
             pos.board(),
             &thread,
             &shared,
-            thread.multipv,
+            thread.report_multipv,
             0,
             &thread.root_pv,
             TTFlag::Exact,
@@ -249,20 +339,22 @@ pub fn id_loop(
             true,
         );
 
+        if let Some(mate) = shared.time_man.mate()
+            && !shared.time_man.mate_found(score)
+        {
+            info.no_mate(mate);
+        }
+
         info.best_move(
             pos.board(),
             best_move.unwrap(),
             ponder_move.filter(|_| thread.ponder),
         );
 
-        shared.best_score.store(score.0, Ordering::Relaxed);
-        shared.best_move.store(
-            best_move.map(|mv| mv.bits()).unwrap_or(0),
-            Ordering::Relaxed,
-        );
-
-        atomic_wait::wake_all(&shared.num_searching);
+        //only mark the searcher idle once everything has been reported
         shared.ttable.age();
+        shared.num_searching.store(0, Ordering::Release);
+        atomic_wait::wake_all(&shared.num_searching);
     }
 }
 
@@ -279,7 +371,7 @@ pub fn search<Node: NodeType>(
     cut_node: bool,
 ) -> Score {
     if !Node::ROOT && (thread.stop || shared.time_man.stop_search(&thread)) {
-        shared.time_man.set_stop(true);
+        shared.time_man.abort();
         thread.stop = true;
         return Score::ZERO;
     }
@@ -292,6 +384,18 @@ pub fn search<Node: NodeType>(
         return Score::draw(thread.nodes.local());
     }
 
+    /*
+    Upcoming Repetition Detection:
+    If we can force a repetition, we can at least draw,
+    so the score is raised to a draw right away.
+    */
+    if !Node::ROOT && alpha < Score::ZERO && pos.has_game_cycle(ply) {
+        alpha = Score::draw(thread.nodes.local());
+        if alpha >= beta {
+            return alpha;
+        }
+    }
+
     if depth <= 0 || ply >= MAX_PLY {
         return q_search::<Node>(pos, thread, shared, ply, alpha, beta);
     }
@@ -307,7 +411,9 @@ pub fn search<Node: NodeType>(
         .is_none()
         .then(|| shared.ttable.fetch(pos.board(), ply))
         .flatten();
-    let tt_move = tt_entry.and_then(|e| e.mv);
+    let tt_move = tt_entry
+        .and_then(|e| e.mv)
+        .or_else(|| (Node::ROOT && thread.tb_root).then(|| thread.root_moves[0]));
     let _tt_noisy = tt_move.is_some_and(|mv| mv.is_noisy());
     let tt_pv = Node::PV || tt_entry.is_some_and(|e| e.pv);
 
@@ -329,6 +435,65 @@ pub fn search<Node: NodeType>(
         }
     }
 
+    /*
+    Syzygy Tablebases:
+    If the position is in the tablebases, we already know
+    the theoretical result. Only probe positions right after
+    a zeroing move, since WDL tables don't know about the 50-move rule.
+    Wins and losses are only bounds, the search can still
+    find something faster (or a mate).
+    */
+    let mut max_score = Score::INFINITE;
+    let mut tb_score = None;
+
+    if !Node::ROOT
+        && skip_move.is_none()
+        && is_syzygy_enabled()
+        && depth >= thread.syzygy_probe_depth as i32 * DEPTH_SCALE
+        && pos.board().occupied().popcnt() <= thread.syzygy_probe_limit as usize
+        && pos.board().halfmove_clock() == 0
+        && pos.board().castle_rights(Color::White).is_none()
+        && pos.board().castle_rights(Color::Black).is_none()
+        && let Some(wdl) = probe_wdl(pos.board())
+    {
+        let (score, flag) = match wdl {
+            WdlProbeResult::Win => (Score::tb_win(ply), TTFlag::LowerBound),
+            WdlProbeResult::Loss => (Score::tb_loss(ply), TTFlag::UpperBound),
+            _ => (Score::ZERO, TTFlag::Exact),
+        };
+
+        if match flag {
+            TTFlag::Exact => true,
+            TTFlag::LowerBound => score >= beta,
+            TTFlag::UpperBound => score <= alpha,
+            TTFlag::None => unreachable!(),
+        } {
+            shared.ttable.store(
+                pos.board(),
+                (depth / DEPTH_SCALE + 6).min(MAX_DEPTH as i32) as u8,
+                ply,
+                Score::NONE,
+                score,
+                None,
+                flag,
+                tt_pv,
+            );
+
+            return score;
+        }
+
+        if Node::PV {
+            match flag {
+                TTFlag::LowerBound => {
+                    tb_score = Some(score);
+                    alpha = alpha.max(score);
+                }
+                TTFlag::UpperBound => max_score = score,
+                _ => {}
+            }
+        }
+    }
+
     let cont_indices = ContIndices::new(&pos);
     let cont_corr_indices = ContCorrIndices::new(&pos);
 
@@ -336,7 +501,9 @@ pub fn search<Node: NodeType>(
     let (raw_eval, static_eval, corr) = if !in_check {
         let raw_eval = if skip_move.is_some() {
             thread.search_stack[ply as usize].raw_eval
-        } else if let Some(entry) = tt_entry {
+        } else if let Some(entry) = tt_entry
+            && entry.eval != Score::NONE
+        {
             entry.eval
         } else {
             scale_eval(pos.eval(), pos.board(), thread.eval_scaling)
@@ -511,7 +678,7 @@ pub fn search<Node: NodeType>(
 
     let mut moves_seen = 0;
     let mut best_move = None;
-    let mut best_score = -Score::INFINITE;
+    let mut best_score = tb_score.unwrap_or(-Score::INFINITE);
     let mut flag = TTFlag::UpperBound;
     let mut move_picker = MovePicker::new(tt_move, W::mp_see_margin());
     let mut noisies: SmallVec<[Move; 64]> = SmallVec::new();
@@ -668,6 +835,15 @@ pub fn search<Node: NodeType>(
             }
         }
 
+        if Node::ROOT && thread.id == 0 && shared.time_man.elapsed().as_millis() >= CURRMOVE_DELAY {
+            thread.info.curr_move(
+                pos.board(),
+                (depth / DEPTH_SCALE) as u8,
+                mv,
+                moves_seen as usize + 1,
+            );
+        }
+
         pos.make_move(mv);
         shared.ttable.prefetch(pos.board());
 
@@ -764,7 +940,16 @@ pub fn search<Node: NodeType>(
         }
 
         if Node::ROOT {
+            let bound = if score <= alpha {
+                TTFlag::UpperBound
+            } else if score >= beta {
+                TTFlag::LowerBound
+            } else {
+                TTFlag::Exact
+            };
+
             thread.root_nodes[mv.src()][mv.dest()] += thread.nodes.local() - nodes;
+            thread.root_scores[mv.src()][mv.dest()] = (score, bound);
         }
 
         if score > best_score {
@@ -825,6 +1010,10 @@ pub fn search<Node: NodeType>(
         };
     }
 
+    if Node::PV {
+        best_score = best_score.min(max_score);
+    }
+
     if skip_move.is_none() {
         shared.ttable.store(
             pos.board(),
@@ -889,7 +1078,7 @@ fn q_search<Node: NodeType>(
     beta: Score,
 ) -> Score {
     if thread.stop || shared.time_man.stop_search(&thread) {
-        shared.time_man.set_stop(true);
+        shared.time_man.abort();
         thread.stop = true;
 
         return Score::ZERO;
@@ -903,6 +1092,13 @@ fn q_search<Node: NodeType>(
         return Score::draw(thread.nodes.local());
     }
 
+    if alpha < Score::ZERO && pos.has_game_cycle(ply) {
+        alpha = Score::draw(thread.nodes.local());
+        if alpha >= beta {
+            return alpha;
+        }
+    }
+
     if ply >= MAX_PLY {
         let raw_eval = scale_eval(pos.eval(), pos.board(), thread.eval_scaling);
         let corr = thread.history.corr(pos, &ContCorrIndices::new(&pos));
@@ -939,6 +1135,7 @@ fn q_search<Node: NodeType>(
     } else {
         let raw_eval = tt_entry
             .map(|e| e.eval)
+            .filter(|&eval| eval != Score::NONE)
             .unwrap_or_else(|| scale_eval(pos.eval(), pos.board(), thread.eval_scaling));
         let corr = thread.history.corr(pos, &ContCorrIndices::new(&pos));
         let static_eval = adjust_eval(raw_eval, corr);
@@ -1,10 +1,10 @@
 use std::{
     fmt::Write,
-    sync::{Arc, atomic::*},
+    sync::{Arc, Mutex, atomic::*},
     thread::JoinHandle,
 };
 
-use pyrrhic_rs::DtzProbeValue;
+use pyrrhic_rs::{DtzProbeValue, WdlProbeResult};
 
 use crate::*;
 
@@ -18,6 +18,7 @@ pub struct SharedData {
     pub num_searching: AtomicU32,
     pub best_score: AtomicI32,
     pub best_move: AtomicU16,
+    pub root_report: Mutex<Option<(Board, u8, Vec<RootMoveReport>)>>,
     pub nodes: Arc<AtomicU64>,
 }
 
@@ -48,6 +49,7 @@ impl Default for SharedData {
             num_searching: AtomicU32::new(0),
             best_score: AtomicI32::new(Score::NONE.0),
             best_move: AtomicU16::new(0),
+            root_report: Mutex::new(None),
             nodes: Arc::new(AtomicU64::new(0)),
         }
     }
@@ -59,17 +61,27 @@ pub struct ThreadData {
     pub nodes: BatchedAtomicCounter,
     pub search_stack: Vec<SearchStack>,
     pub root_nodes: [[u64; Square::COUNT]; Square::COUNT],
+    pub root_scores: [[(Score, TTFlag); Square::COUNT]; Square::COUNT],
     pub root_pv: PrincipalVariation,
     pub exclude_moves: MoveList,
     pub root_moves: MoveList,
+    pub tb_root: bool,
     pub windows: Vec<Window>,
     pub history: Box<History>,
     pub nmp_min_ply: u16,
     pub sel_depth: u16,
     pub multipv: u8,
+    //MultiPV lines shown to the GUI, `multipv` can be higher when limiting strength
+    pub report_multipv: u8,
+    pub skill: Option<Skill>,
+    pub skill_seed: u64,
+    pub syzygy_probe_depth: u8,
+    pub syzygy_probe_limit: u8,
     pub eval_scaling: bool,
     pub ponder: bool,
     pub frc: bool,
+    pub verbose: bool,
+    pub info: SearchInfo,
     pub id: usize,
 }
 
@@ -82,16 +94,25 @@ impl ThreadData {
             search_stack: vec![SearchStack::default(); MAX_PLY as usize + 1],
             windows: Vec::new(),
             root_moves: MoveList::empty(),
+            tb_root: false,
             exclude_moves: MoveList::empty(),
             root_nodes: [[0; Square::COUNT]; Square::COUNT],
+            root_scores: [[(Score::NONE, TTFlag::None); Square::COUNT]; Square::COUNT],
             root_pv: PrincipalVariation::default(),
             history: unsafe { Box::new_zeroed().assume_init() },
             nmp_min_ply: 0,
             sel_depth: 0,
             eval_scaling: true,
             multipv: 1,
+            report_multipv: 1,
+            skill: None,
+            skill_seed: 0,
+            syzygy_probe_depth: 1,
+            syzygy_probe_limit: 7,
             ponder: false,
             frc: false,
+            verbose: false,
+            info: SearchInfo::none(),
             id,
         }
     }
@@ -102,16 +123,25 @@ impl ThreadData {
         self.nodes.reset();
         self.search_stack = vec![SearchStack::default(); MAX_PLY as usize + 1];
         self.root_nodes = [[0; Square::COUNT]; Square::COUNT];
+        self.root_scores = [[(Score::NONE, TTFlag::None); Square::COUNT]; Square::COUNT];
         self.root_pv = PrincipalVariation::default();
         self.exclude_moves.clear();
         self.root_moves.clear();
+        self.tb_root = false;
         self.nmp_min_ply = 0;
         self.windows.clear();
         self.sel_depth = 0;
         self.eval_scaling = true;
         self.multipv = 1;
+        self.report_multipv = 1;
+        self.skill = None;
+        self.skill_seed = 0;
+        self.syzygy_probe_depth = 1;
+        self.syzygy_probe_limit = 7;
         self.ponder = false;
         self.frc = false;
+        self.verbose = false;
+        self.info = SearchInfo::none();
     }
 }
 
@@ -196,6 +226,8 @@ pub enum ThreadCommand {
         pos: Position,
         options: EngineOptions,
         root_moves: MoveList,
+        tb_root: bool,
+        verbose: bool,
         info: SearchInfo,
     },
     SetShared(Arc<SharedData>),
@@ -217,6 +249,8 @@ impl Searcher {
         options: EngineOptions,
         info: SearchInfo,
     ) {
+        assert!(!self.is_searching(), "Called `search()` while searching");
+
         self.shared.num_searching.store(1, Ordering::Relaxed);
         self.shared.time_man.init(
             pos.stm(),
@@ -224,6 +258,9 @@ impl Searcher {
             options.move_overhead,
             options.soft_target,
         );
+        if let Some(skill) = options.skill() {
+            self.shared.time_man.cap_nodes(skill.nodes);
+        }
 
         let focused = limits
             .iter()
@@ -236,24 +273,21 @@ impl Searcher {
             })
             .unwrap_or_else(|| pos.board().gen_moves());
 
+        /*
+        At the root, DTZ tables are used to rank the moves rather than
+        to pick one outright. Only moves that preserve the best result
+        (taking the 50-move rule into account) are searched, and the move
+        that zeroes the 50-move counter fastest is searched first.
+        */
+        let mut tb_root = false;
         if !focused
             && is_syzygy_enabled()
             && let Some(dtz) = probe_dtz(pos.board())
         {
-            let mut best_moves = MoveList::empty();
-            let mut best_score = 0u8;
+            let mut ranked = Vec::new();
 
             for value in &dtz.moves[..dtz.num_moves] {
                 if let DtzProbeValue::DtzResult(result) = value {
-                    let move_score = result.wdl as u8;
-                    if move_score < best_score {
-                        continue;
-                    }
-                    if move_score > best_score {
-                        best_score = move_score;
-                        best_moves.clear();
-                    }
-
                     let src = Square::index(result.from_square as usize);
                     let dest = Square::index(result.to_square as usize);
                     let promotion = match Piece::index(result.promotion as usize) {
@@ -273,22 +307,40 @@ impl Searcher {
                     if let Some(mv) = Move::parse(pos.board(), &move_str)
                         && root_moves.contains(&mv)
                     {
-                        best_moves.push(mv);
+                        ranked.push((mv, result.wdl as u8, result.dtz as i32));
                     }
                 }
             }
 
-            root_moves = best_moves;
+            if let Some(best_wdl) = ranked.iter().map(|&(_, wdl, _)| wdl).max() {
+                let winning = best_wdl > WdlProbeResult::Draw as u8;
+                ranked.retain(|&(_, wdl, _)| wdl == best_wdl);
+                ranked.sort_by_key(|&(_, _, dtz)| if winning { dtz } else { -dtz });
+
+                root_moves.clear();
+                for (mv, _, _) in ranked {
+                    root_moves.push(mv);
+                }
+                tb_root = true;
+            }
         }
 
+        let verbose = limits.iter().any(|l| matches!(l, SearchLimit::Verbose));
         self.command_sender.send(ThreadCommand::Go {
             pos,
             options,
             root_moves,
+            tb_root,
+            verbose,
             info,
         });
     }
 
+    #[inline]
+    pub fn num_threads(&self) -> usize {
+        self.search_threads.len()
+    }
+
     #[inline]
     pub fn set_threads(&mut self, threads: u32) {
         assert!(
@@ -332,6 +384,7 @@ impl Searcher {
             num_searching: AtomicU32::new(0),
             best_score: AtomicI32::new(Score::NONE.0),
             best_move: AtomicU16::new(0),
+            root_report: Mutex::new(None),
             nodes: Arc::new(AtomicU64::new(0)),
         });
         self.command_sender
@@ -346,6 +399,18 @@ impl Searcher {
         self.command_sender.send(ThreadCommand::NewGame);
     }
 
+    #[inline]
+    pub fn save_hash(&self, path: &str) -> Result<(), HashFileError> {
+        assert!(!self.is_searching(), "Called `save_hash()` while searching");
+        self.shared.ttable.save(path)
+    }
+
+    #[inline]
+    pub fn load_hash(&self, path: &str) -> Result<(), HashFileError> {
+        assert!(!self.is_searching(), "Called `load_hash()` while searching");
+        self.shared.ttable.load(path)
+    }
+
     #[inline]
     pub fn quit(&mut self) {
         self.shared.time_man.set_stop(true);
@@ -361,12 +426,21 @@ impl Searcher {
         self.shared.time_man.set_stop(true);
     }
 
+    #[inline]
+    pub fn ponderhit(&self) {
+        assert!(
+            self.is_searching(),
+            "Called `ponderhit()` while not searching"
+        );
+        self.shared.time_man.ponderhit();
+    }
+
     #[inline]
     pub fn wait(&self) {
-        let mut num_searching = self.shared.num_searching.load(Ordering::Relaxed);
+        let mut num_searching = self.shared.num_searching.load(Ordering::Acquire);
         while num_searching != 0 {
             atomic_wait::wait(&self.shared.num_searching, num_searching);
-            num_searching = self.shared.num_searching.load(Ordering::Relaxed);
+            num_searching = self.shared.num_searching.load(Ordering::Acquire);
         }
     }
 
@@ -374,6 +448,12 @@ impl Searcher {
     pub fn is_searching(&self) -> bool {
         self.shared.num_searching.load(Ordering::Relaxed) != 0
     }
+
+    //The search has been stopped and is only reporting its result
+    #[inline]
+    pub fn is_stopping(&self) -> bool {
+        self.is_searching() && self.shared.time_man.stopped()
+    }
 }
 
 impl Default for Searcher {
@@ -411,16 +491,28 @@ fn thread_loop(mut rx: Receiver<ThreadCommand>, mut shared: Arc<SharedData>, id:
                 pos,
                 options,
                 root_moves,
+                tb_root,
+                verbose,
                 info,
             } => {
                 shared.num_searching.fetch_add(1, Ordering::Relaxed);
 
                 thread.reset();
                 thread.root_moves = root_moves;
-                thread.multipv = options.multipv;
+                thread.tb_root = tb_root;
+                thread.skill = options.skill();
+                thread.skill_seed = options.skill_seed;
+                thread.report_multipv = options.multipv;
+                thread.multipv = options
+                    .multipv
+                    .max(thread.skill.map_or(1, |skill| skill.multipv));
+                thread.syzygy_probe_depth = options.syzygy_probe_depth;
+                thread.syzygy_probe_limit = options.syzygy_probe_limit;
                 thread.eval_scaling = options.eval_scaling;
                 thread.ponder = options.ponder;
                 thread.frc = options.frc;
+                thread.verbose = verbose;
+                thread.info = info.clone();
 
                 id_loop(pos, &mut thread, &shared, info);
             }
@@ -0,0 +1,93 @@
+use rand::{RngExt, SeedableRng, rngs::SmallRng};
+
+use crate::*;
+
+/*----------------------------------------------------------------*/
+
+pub const MAX_SKILL_LEVEL: u8 = 20;
+
+/*
+Strength Limiting:
+Weaker play is produced by capping the nodes of the search,
+then picking a move among the MultiPV root moves, where worse
+moves get picked more often the higher the temperature.
+*/
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Skill {
+    pub multipv: u8,
+    pub temperature: f64,
+    pub nodes: u64,
+}
+
+/*
+Table mapping skill levels to (MultiPV, temperature, nodes).
+Levels in between two entries are interpolated.
+The levels are only ordered by strength, they haven't been calibrated
+against rated opponents, which is why no `UCI_Elo` option is offered.
+*/
+pub const SKILL_TABLE: [(u8, u8, f64, u64); 6] = [
+    (0, 6, 300.0, 200),
+    (4, 5, 180.0, 1000),
+    (8, 4, 100.0, 5000),
+    (12, 3, 50.0, 25000),
+    (16, 2, 20.0, 150000),
+    (20, 1, 0.0, 1000000),
+];
+
+impl Skill {
+    pub fn from_level(level: u8) -> Option<Skill> {
+        if level >= MAX_SKILL_LEVEL {
+            return None;
+        }
+
+        let upper = SKILL_TABLE
+            .iter()
+            .position(|&(l, ..)| l >= level)
+            .unwrap()
+            .max(1);
+        let (lo_level, lo_multipv, lo_temp, lo_nodes) = SKILL_TABLE[upper - 1];
+        let (hi_level, hi_multipv, hi_temp, hi_nodes) = SKILL_TABLE[upper];
+        let t = (level - lo_level) as f64 / (hi_level - lo_level) as f64;
+
+        Some(Skill {
+            multipv: (lo_multipv as f64 + (hi_multipv as f64 - lo_multipv as f64) * t).round()
+                as u8,
+            temperature: lo_temp + (hi_temp - lo_temp) * t,
+            nodes: (lo_nodes as f64 * (hi_nodes as f64 / lo_nodes as f64).powf(t)) as u64,
+        })
+    }
+
+    /*----------------------------------------------------------------*/
+
+    pub fn pick(&self, scores: &[Score], board: &Board, seed: u64) -> Option<usize> {
+        let (best, &best_score) = scores.iter().enumerate().max_by_key(|&(_, score)| score)?;
+        if self.temperature <= 0.0 || best_score.is_decisive() {
+            return Some(best);
+        }
+
+        let weights = scores
+            .iter()
+            .map(|&score| {
+                if score.is_decisive() {
+                    0.0
+                } else {
+                    ((score - best_score).0 as f64 / self.temperature).exp()
+                }
+            })
+            .collect::<Vec<_>>();
+        let total = weights.iter().sum::<f64>();
+
+        let mut rng = SmallRng::seed_from_u64(seed ^ board.hash());
+        let mut target = rng.random_range(0.0..total);
+
+        for (i, weight) in weights.into_iter().enumerate() {
+            if target < weight {
+                return Some(i);
+            }
+
+            target -= weight;
+        }
+
+        Some(best)
+    }
+}
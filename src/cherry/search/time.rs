@@ -17,6 +17,24 @@ pub enum SearchLimit {
     MovesToGo(u16),
     Nodes(u64),
     Depth(u8),
+    Mate(u16),
+    Ponder,
+    Verbose,
+}
+
+impl SearchLimit {
+    //Searches without any budget, like `go infinite` or `go infinite verbose`
+    #[inline]
+    pub fn is_analysis(limits: &[SearchLimit]) -> bool {
+        use SearchLimit::*;
+
+        !limits.iter().any(|limit| {
+            matches!(
+                limit,
+                WhiteTime(_) | BlackTime(_) | MoveTime(_) | Depth(_) | Nodes(_) | MovesToGo(_)
+            )
+        })
+    }
 }
 
 /*----------------------------------------------------------------*/
@@ -35,6 +53,14 @@ pub struct TimeManager {
     soft_nodes: AtomicU64,
     hard_nodes: AtomicU64,
     depth: AtomicU8,
+    mate: AtomicU16,
+}
+
+impl Default for TimeManager {
+    #[inline]
+    fn default() -> Self {
+        TimeManager::new()
+    }
 }
 
 impl TimeManager {
@@ -54,22 +80,23 @@ impl TimeManager {
             soft_nodes: AtomicU64::new(u64::MAX),
             hard_nodes: AtomicU64::new(u64::MAX),
             depth: AtomicU8::new(MAX_DEPTH),
+            mate: AtomicU16::new(0),
         }
     }
 
     #[inline]
     pub fn init(&self, stm: Color, limits: &[SearchLimit], overhead: u64, soft_target: bool) {
-        self.set_stop(false);
-
         let mut inc = [0; Color::COUNT];
         let mut time = [u64::MAX; Color::COUNT];
         let mut moves_to_go = None;
         let mut move_time = None;
         let mut nodes = u64::MAX;
         let mut depth = MAX_DEPTH;
+        let mut mate = 0;
         let mut infinite = true;
         let mut check_time = false;
         let mut no_manage = false;
+        let mut ponder = false;
 
         for limit in limits {
             use SearchLimit::*;
@@ -84,11 +111,14 @@ impl TimeManager {
                 MovesToGo(n) => moves_to_go = Some(n),
                 Nodes(n) => nodes = nodes.min(n),
                 Depth(d) => depth = depth.min(d),
+                Mate(n) => mate = n,
+                Ponder => ponder = true,
+                Verbose => {}
             }
 
             if matches!(
                 limit,
-                WhiteTime(..) | BlackTime(..) | MoveTime(..) | Depth(..) | Nodes(..)
+                WhiteTime(..) | BlackTime(..) | MoveTime(..) | Depth(..) | Nodes(..) | Mate(..)
             ) {
                 infinite = false;
             }
@@ -107,6 +137,7 @@ impl TimeManager {
         self.no_manage.store(no_manage, Ordering::Relaxed);
 
         self.depth.store(depth, Ordering::Relaxed);
+        self.mate.store(mate, Ordering::Relaxed);
         self.soft_nodes.store(nodes, Ordering::Relaxed);
         if soft_target {
             self.hard_nodes
@@ -149,6 +180,26 @@ impl TimeManager {
         }
 
         self.start.store(Instant::now(), Ordering::Relaxed);
+        self.stop.store(
+            if ponder { TimeManager::PONDER } else { 0 },
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Caps the nodes of the search without making it a timed one,
+    /// so `go infinite` still waits for `stop`.
+    #[inline]
+    pub fn cap_nodes(&self, nodes: u64) {
+        self.soft_nodes.fetch_min(nodes, Ordering::Relaxed);
+        self.hard_nodes.fetch_min(nodes, Ordering::Relaxed);
+    }
+
+    /// Switches a ponder search over to its clock limits, counting elapsed time from now.
+    #[inline]
+    pub fn ponderhit(&self) {
+        self.start.store(Instant::now(), Ordering::Relaxed);
+        self.stop.fetch_and(!TimeManager::PONDER, Ordering::Relaxed);
+        atomic_wait::wake_all(&self.stop);
     }
 
     #[inline]
@@ -190,38 +241,84 @@ impl TimeManager {
 
     #[inline]
     pub fn set_stop(&self, stop: bool) {
-        self.stop.store(stop as u32, Ordering::Relaxed);
+        if stop {
+            self.stop.fetch_or(TimeManager::STOP, Ordering::Relaxed);
+        } else {
+            self.stop.fetch_and(!TimeManager::STOP, Ordering::Relaxed);
+        }
+
         if self.infinite() {
             atomic_wait::wake_all(&self.stop);
         }
     }
 
+    /*
+    Stops all search threads once a limit is hit, without counting as a `stop` command,
+    so that infinite and ponder searches still wait for one before reporting their move.
+    */
+    #[inline]
+    pub fn abort(&self) {
+        self.stop.fetch_or(TimeManager::ABORT, Ordering::Relaxed);
+    }
+
     #[inline]
     pub fn wait_for_stop(&self) {
-        while !self.should_stop() {
-            atomic_wait::wait(&self.stop, 0);
+        loop {
+            let stop = self.stop.load(Ordering::Relaxed);
+            if stop & TimeManager::STOP != 0
+                || (stop & TimeManager::PONDER == 0 && !self.infinite.load(Ordering::Relaxed))
+            {
+                break;
+            }
+
+            atomic_wait::wait(&self.stop, stop);
         }
     }
 
     #[inline]
     pub fn stop_search(&self, thread: &ThreadData) -> bool {
-        self.should_stop()
-            || thread.nodes.global() >= self.hard_nodes.load(Ordering::Relaxed)
-            || (thread.nodes.local().is_multiple_of(1024)
-                && thread.id == 0
-                && self.check_time.load(Ordering::Relaxed)
-                && self.elapsed().as_millis() as u64 > self.hard_time.load(Ordering::Relaxed))
+        let stop = self.stop.load(Ordering::Relaxed);
+
+        stop & (TimeManager::STOP | TimeManager::ABORT) != 0
+            || (stop & TimeManager::PONDER == 0
+                && (thread.nodes.global() >= self.hard_nodes.load(Ordering::Relaxed)
+                    || (thread.nodes.local().is_multiple_of(1024)
+                        && thread.id == 0
+                        && self.check_time.load(Ordering::Relaxed)
+                        && self.elapsed().as_millis() as u64
+                            > self.hard_time.load(Ordering::Relaxed))))
     }
 
     #[inline]
-    pub fn stop_id(&self, depth: u8, nodes: u64) -> bool {
+    pub fn stop_id(&self, depth: u8, nodes: u64, score: Score) -> bool {
+        if self.pondering() {
+            return self.should_stop() || depth == MAX_DEPTH;
+        }
+
         self.should_stop()
             || depth >= self.depth.load(Ordering::Relaxed)
             || nodes >= self.soft_nodes.load(Ordering::Relaxed)
+            || self.mate_found(score)
             || (self.check_time.load(Ordering::Relaxed)
                 && self.elapsed().as_millis() as u64 > self.soft_time.load(Ordering::Relaxed))
     }
 
+    #[inline]
+    pub fn mate(&self) -> Option<u16> {
+        let mate = self.mate.load(Ordering::Relaxed);
+
+        if mate != 0 { Some(mate) } else { None }
+    }
+
+    #[inline]
+    pub fn mate_found(&self, score: Score) -> bool {
+        self.mate().is_some_and(|mate| {
+            score
+                .mate_in()
+                .is_some_and(|ply| ply > 0 && (ply as i32) < 2 * mate as i32)
+        })
+    }
+
     #[inline]
     pub fn elapsed(&self) -> Duration {
         self.start.load(Ordering::Relaxed).elapsed()
@@ -229,11 +326,27 @@ impl TimeManager {
 
     #[inline]
     pub fn should_stop(&self) -> bool {
-        self.stop.load(Ordering::Relaxed) != 0
+        self.stop.load(Ordering::Relaxed) & (TimeManager::STOP | TimeManager::ABORT) != 0
+    }
+
+    #[inline]
+    pub fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed) & TimeManager::STOP != 0
+    }
+
+    #[inline]
+    pub fn pondering(&self) -> bool {
+        self.stop.load(Ordering::Relaxed) & TimeManager::PONDER != 0
     }
 
     #[inline]
     pub fn infinite(&self) -> bool {
-        self.infinite.load(Ordering::Relaxed)
+        self.infinite.load(Ordering::Relaxed) || self.pondering()
     }
+
+    /*----------------------------------------------------------------*/
+
+    const STOP: u32 = 1 << 0;
+    const PONDER: u32 = 1 << 1;
+    const ABORT: u32 = 1 << 2;
 }
@@ -1,4 +1,11 @@
-use core::{arch::x86_64::*, ptr, sync::atomic::*};
+use core::sync::atomic::*;
+#[cfg(target_arch = "x86_64")]
+use core::{arch::x86_64::*, ptr};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
 
 use crate::*;
 
@@ -167,6 +174,39 @@ impl TTCluster {
 
 /*----------------------------------------------------------------*/
 
+const HASH_FILE_MAGIC: &[u8; 8] = b"CHRYHASH";
+const HASH_FILE_VERSION: u32 = 1;
+
+#[derive(thiserror::Error, Debug)]
+pub enum HashFileError {
+    #[error("I/O Error: `{0}`")]
+    Io(#[from] io::Error),
+    #[error("Not a Cherry hash file")]
+    InvalidMagic,
+    #[error("Unsupported hash file version: `{0}`")]
+    UnsupportedVersion(u32),
+    #[error(
+        "Hash file doesn't match the current table: expected {expected} clusters, found {found}"
+    )]
+    SizeMismatch { expected: u64, found: u64 },
+    #[error("Hash file is corrupted (checksum mismatch)")]
+    ChecksumMismatch,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Checksum(u64);
+
+impl Checksum {
+    #[inline]
+    fn update(&mut self, value: u64) {
+        self.0 = (self.0 ^ value)
+            .wrapping_mul(0x9E3779B97F4A7C15)
+            .rotate_left(31);
+    }
+}
+
+/*----------------------------------------------------------------*/
+
 pub struct TTable {
     clusters: Box<[TTCluster]>,
     age: AtomicU8,
@@ -205,7 +245,9 @@ impl TTable {
     }
 
     #[inline]
+    #[cfg_attr(not(target_arch = "x86_64"), allow(unused_variables))]
     pub fn prefetch(&self, board: &Board) {
+        #[cfg(target_arch = "x86_64")]
         unsafe {
             _mm_prefetch::<_MM_HINT_T0>(
                 ptr::from_ref(&self.clusters[self.index(board.hash())]).cast(),
@@ -301,6 +343,111 @@ impl TTable {
         });
     }
 
+    /*
+    File Layout (little endian):
+    - Magic, Version, Cluster Count, Age
+    - Clusters (data followed by keys)
+    - Checksum over the clusters
+    */
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), HashFileError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let mut checksum = Checksum(0);
+
+        writer.write_all(HASH_FILE_MAGIC)?;
+        writer.write_all(&HASH_FILE_VERSION.to_le_bytes())?;
+        writer.write_all(&(self.clusters.len() as u64).to_le_bytes())?;
+        writer.write_all(&[self.age.load(Ordering::Relaxed)])?;
+
+        for cluster in self.clusters.iter() {
+            for data in &cluster.data {
+                let data = data.load(Ordering::Relaxed);
+                checksum.update(data);
+                writer.write_all(&data.to_le_bytes())?;
+            }
+
+            for key in &cluster.key {
+                let key = key.load(Ordering::Relaxed);
+                checksum.update(key as u64);
+                writer.write_all(&key.to_le_bytes())?;
+            }
+        }
+
+        writer.write_all(&checksum.0.to_le_bytes())?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(&self, path: P) -> Result<(), HashFileError> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != HASH_FILE_MAGIC {
+            return Err(HashFileError::InvalidMagic);
+        }
+
+        let mut buf = [0u8; 8];
+        reader.read_exact(&mut buf[..4])?;
+        let version = u32::from_le_bytes(buf[..4].try_into().unwrap());
+        if version != HASH_FILE_VERSION {
+            return Err(HashFileError::UnsupportedVersion(version));
+        }
+
+        reader.read_exact(&mut buf)?;
+        let num_clusters = u64::from_le_bytes(buf);
+        if num_clusters != self.clusters.len() as u64 {
+            return Err(HashFileError::SizeMismatch {
+                expected: self.clusters.len() as u64,
+                found: num_clusters,
+            });
+        }
+
+        let mut age = [0u8; 1];
+        reader.read_exact(&mut age)?;
+
+        //Stream straight into the table and wipe it if the file turns out to be bad
+        let result = self.read_clusters(&mut reader);
+        if result.is_err() {
+            self.clear(1);
+            return result;
+        }
+
+        self.age.store(age[0] & AGE_MASK, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    fn read_clusters(&self, reader: &mut impl Read) -> Result<(), HashFileError> {
+        let mut checksum = Checksum(0);
+        let mut buf = [0u8; 8];
+
+        for cluster in self.clusters.iter() {
+            for data in &cluster.data {
+                reader.read_exact(&mut buf)?;
+                let value = u64::from_le_bytes(buf);
+                checksum.update(value);
+                data.store(value, Ordering::Relaxed);
+            }
+
+            for key in &cluster.key {
+                reader.read_exact(&mut buf[..2])?;
+                let value = u16::from_le_bytes(buf[..2].try_into().unwrap());
+                checksum.update(value as u64);
+                key.store(value, Ordering::Relaxed);
+            }
+        }
+
+        reader.read_exact(&mut buf)?;
+        if u64::from_le_bytes(buf) != checksum.0 {
+            return Err(HashFileError::ChecksumMismatch);
+        }
+
+        Ok(())
+    }
+
+    /*----------------------------------------------------------------*/
+
     #[inline]
     pub fn age(&self) {
         self.age.fetch_add(1, Ordering::Relaxed);
@@ -314,3 +461,65 @@ impl TTable {
         ((u128::from(hash) * self.clusters.len() as u128) >> 64) as usize
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn hash_file_round_trip() {
+        let board = Board::startpos();
+        let mv = Move::parse(&board, "e2e4");
+        let path = TempFile::new("round-trip.hash");
+
+        let ttable = TTable::new(1);
+        ttable.age();
+        ttable.store(&board, 12, 0, Score(25), Score(40), mv, TTFlag::Exact, true);
+        ttable.save(&path).unwrap();
+
+        let loaded = TTable::new(1);
+        loaded.load(&path).unwrap();
+        let resized = TTable::new(2).load(&path);
+
+        let entry = loaded.fetch(&board, 0).unwrap();
+        assert_eq!(entry.depth, 12);
+        assert_eq!(entry.eval, Score(25));
+        assert_eq!(entry.score, Score(40));
+        assert_eq!(entry.mv, mv);
+        assert_eq!(entry.flag, TTFlag::Exact);
+        assert!(entry.pv);
+        assert_eq!(loaded.age.load(Ordering::Relaxed), 1);
+        assert!(matches!(resized, Err(HashFileError::SizeMismatch { .. })));
+    }
+
+    #[test]
+    fn hash_file_corrupted() {
+        let board = Board::startpos();
+        let path = TempFile::new("corrupted.hash");
+
+        let ttable = TTable::new(1);
+        ttable.store(
+            &board,
+            5,
+            0,
+            Score(0),
+            Score(10),
+            None,
+            TTFlag::LowerBound,
+            false,
+        );
+        ttable.save(&path).unwrap();
+
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[64] ^= 1;
+        fs::write(&path, bytes).unwrap();
+
+        let loaded = TTable::new(1);
+        let result = loaded.load(&path);
+
+        assert!(matches!(result, Err(HashFileError::ChecksumMismatch)));
+        assert!(loaded.fetch(&board, 0).is_none());
+    }
+}
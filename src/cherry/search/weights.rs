@@ -1,5 +1,7 @@
 #[cfg(feature = "tune")]
 use core::cell::SyncUnsafeCell;
+use core::fmt::Write;
+use std::{fs, io, path::Path};
 
 use crate::*;
 
@@ -42,6 +44,16 @@ static LOG: [f32; MAX_PLY as usize] = [
 
 /*----------------------------------------------------------------*/
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WeightParam {
+    pub name: &'static str,
+    pub tunable: &'static str,
+    pub ty: &'static str,
+    pub default: i64,
+    pub min: i64,
+    pub max: i64,
+}
+
 macro_rules! weights {
     ($($name:ident | $tunable:ident : $ty:ty => $default:literal | $min:literal..=$max:literal;)*) => {
         pub struct W;
@@ -52,6 +64,19 @@ macro_rules! weights {
         )*
 
         impl W {
+            pub const PARAMS: &[WeightParam] = &[
+                $(
+                    WeightParam {
+                        name: stringify!($name),
+                        tunable: stringify!($tunable),
+                        ty: stringify!($ty),
+                        default: $default as i64,
+                        min: $min as i64,
+                        max: $max as i64,
+                    },
+                )*
+            ];
+
             $(
 
                 #[cfg(not(feature = "tune"))]
@@ -61,51 +86,40 @@ macro_rules! weights {
             )*
 
             #[cfg(feature = "tune")]
-            pub fn set_weight(name: &str, value: String) {
-                match name {
-                    $(
-                        stringify!($tunable) => {
-                            let value = match value.parse::<$ty>() {
-                                Ok(value) => value,
-                                Err(e) => {
-                                    println!("info string {:?}", UciParseError::InvalidInteger(e));
-                                    return;
-                                }
-                            };
-
-                            if value > $max || value < $min {
-                                println!("info string Invalid {} value: `{value}`", stringify!($tunable));
-                                return;
-                            }
-
+            pub fn register(options: &mut OptionRegistry) {
+                $(
+                    options.add(
+                        stringify!($tunable),
+                        OptionKind::Spin { default: $default as i64, min: $min as i64, max: $max as i64 },
+                        |_, value| {
                             unsafe {
-                                *$tunable.get() = value;
+                                *$tunable.get() = value.spin() as $ty;
                             }
+
+                            Ok(())
                         },
-                    )*
-                    _ => println!("info string Unknown Option: `{name}`"),
-                }
+                    );
+                )*
             }
 
-            #[cfg(feature = "tune")]
-            pub fn is_weight(name: &str) -> bool {
-                match name {
-                    $(stringify!($tunable) => true,)*
-                    _ => false
-                }
+            pub fn snapshot() -> Vec<i64> {
+                vec![$(W::$name() as i64),*]
             }
 
             #[cfg(feature = "tune")]
-            pub fn print_spsa() {
+            pub fn restore(values: &[i64]) {
+                let mut values = values.iter();
                 $(
-                    println!("{}, int, {:.1}, {:.1}, {:.1}, {:.2}, 0.002", stringify!($tunable), $default as f32, $min as f32, $max as f32, ($max as f32 - $min as f32).abs() / 25.0);
+                    unsafe {
+                        *$tunable.get() = *values.next().unwrap() as $ty;
+                    }
                 )*
             }
 
             #[cfg(feature = "tune")]
-            pub fn print_uci() {
+            pub fn print_spsa() {
                 $(
-                    println!("option name {} type spin default {} min {} max {}", stringify!($tunable), $default, $min, $max);
+                    println!("{}, int, {:.1}, {:.1}, {:.1}, {:.2}, 0.002", stringify!($tunable), $default as f32, $min as f32, $max as f32, ($max as f32 - $min as f32).abs() / 25.0);
                 )*
             }
         }
@@ -114,6 +128,155 @@ macro_rules! weights {
 
 /*----------------------------------------------------------------*/
 
+#[derive(thiserror::Error, Debug)]
+pub enum WeightsError {
+    #[error("I/O Error: `{0}`")]
+    Io(#[from] io::Error),
+    #[error("Invalid entry in weights file: `{0}`")]
+    InvalidEntry(String),
+    #[error("Unknown weight: `{0}`")]
+    UnknownWeight(String),
+    #[error("Duplicate weight: `{0}`")]
+    DuplicateWeight(String),
+    #[error("Value of `{0}` is out of range: {1} (expected {2}..={3})")]
+    OutOfRange(&'static str, i64, i64, i64),
+    #[error("Weights can only be changed with the `tune` feature")]
+    NotTunable,
+}
+
+/*
+Weights files are either a flat JSON object (`.json`)
+or one `<TUNABLE> <value>` pair per line (anything else).
+Both tunable and function names are accepted when loading,
+lines starting with `#` and the `iteration` line of SPSA checkpoints are skipped.
+*/
+#[inline]
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+fn parse_weights(contents: &str, json: bool) -> Result<Vec<(String, f64)>, WeightsError> {
+    let invalid = |entry: &str| WeightsError::InvalidEntry(entry.trim().to_string());
+    let mut entries = Vec::new();
+
+    if json {
+        let body = contents
+            .trim()
+            .strip_prefix('{')
+            .and_then(|body| body.strip_suffix('}'))
+            .ok_or_else(|| invalid(contents))?;
+
+        for entry in body.split(',').filter(|entry| !entry.trim().is_empty()) {
+            let (name, value) = entry.split_once(':').ok_or_else(|| invalid(entry))?;
+            let name = name
+                .trim()
+                .strip_prefix('"')
+                .and_then(|name| name.strip_suffix('"'))
+                .ok_or_else(|| invalid(entry))?;
+            let value = value.trim().parse::<f64>().map_err(|_| invalid(entry))?;
+
+            entries.push((name.to_string(), value));
+        }
+    } else {
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut tokens = line.split_ascii_whitespace();
+            let (Some(name), Some(value), None) = (tokens.next(), tokens.next(), tokens.next())
+            else {
+                return Err(invalid(line));
+            };
+
+            if name == "iteration" {
+                continue;
+            }
+
+            let value = value.parse::<f64>().map_err(|_| invalid(line))?;
+            entries.push((name.to_string(), value));
+        }
+    }
+
+    Ok(entries)
+}
+
+impl W {
+    pub fn save<P: AsRef<Path>>(path: P) -> Result<(), WeightsError> {
+        let path = path.as_ref();
+        let values = W::snapshot();
+        let mut contents = String::new();
+
+        if is_json(path) {
+            contents.push_str("{\n");
+            for (i, (param, value)) in W::PARAMS.iter().zip(&values).enumerate() {
+                let comma = if i + 1 < W::PARAMS.len() { "," } else { "" };
+                writeln!(contents, "    \"{}\": {value}{comma}", param.tunable).unwrap();
+            }
+            contents.push_str("}\n");
+        } else {
+            for (param, value) in W::PARAMS.iter().zip(&values) {
+                writeln!(contents, "{} {value}", param.tunable).unwrap();
+            }
+        }
+
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /*
+    Every entry is validated before anything is applied,
+    so a bad file never leaves the weights half-loaded.
+    Weights missing from the file keep their current value.
+    */
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<usize, WeightsError> {
+        let path = path.as_ref();
+        let entries = parse_weights(&fs::read_to_string(path)?, is_json(path))?;
+        let mut values = W::snapshot();
+        let mut seen = vec![false; W::PARAMS.len()];
+
+        for (name, value) in entries {
+            let Some(index) = W::PARAMS
+                .iter()
+                .position(|param| param.tunable == name || param.name == name)
+            else {
+                return Err(WeightsError::UnknownWeight(name));
+            };
+
+            if seen[index] {
+                return Err(WeightsError::DuplicateWeight(name));
+            }
+
+            let param = &W::PARAMS[index];
+            let value = value.round() as i64;
+            if !(param.min.min(param.max)..=param.max.max(param.min)).contains(&value) {
+                return Err(WeightsError::OutOfRange(
+                    param.tunable,
+                    value,
+                    param.min,
+                    param.max,
+                ));
+            }
+
+            values[index] = value;
+            seen[index] = true;
+        }
+
+        #[cfg(not(feature = "tune"))]
+        if values != W::snapshot() {
+            return Err(WeightsError::NotTunable);
+        }
+
+        #[cfg(feature = "tune")]
+        W::restore(&values);
+
+        Ok(seen.iter().filter(|&&seen| seen).count())
+    }
+}
+
+/*----------------------------------------------------------------*/
+
 weights! {
     pawn_corr    | PAWN_CORR:    i32 => 71  | 32..=96;
     minor_corr   | MINOR_CORR:   i32 => 57  | 32..=96;
@@ -473,3 +636,73 @@ impl W {
         DEPTH_SCALE * (base + LOG[depth as usize] * LOG[moves_seen as usize] / div) as i32
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_str(name: &str, contents: &str) -> Result<usize, WeightsError> {
+        let path = TempFile::new(name);
+        fs::write(&path, contents).unwrap();
+
+        W::load(&path)
+    }
+
+    #[test]
+    fn weights_round_trip() {
+        for name in ["round-trip.json", "round-trip.txt"] {
+            let path = TempFile::new(name);
+            W::save(&path).unwrap();
+            let result = W::load(&path);
+
+            assert_eq!(result.unwrap(), W::PARAMS.len(), "{name}");
+            assert_eq!(
+                W::snapshot(),
+                W::PARAMS.iter().map(|p| p.default).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn weights_parsing() {
+        let json = "{\n    \"PAWN_CORR\": 71,\n    \"minor_corr\": 57.0\n}\n";
+        let txt = "# comment\niteration 3\nPAWN_CORR 71\n\nminor_corr 57.0\n";
+        let expected = vec![
+            (String::from("PAWN_CORR"), 71.0),
+            (String::from("minor_corr"), 57.0),
+        ];
+
+        assert_eq!(parse_weights(json, true).unwrap(), expected);
+        assert_eq!(parse_weights(txt, false).unwrap(), expected);
+        assert!(matches!(
+            parse_weights("PAWN_CORR 71 72", false),
+            Err(WeightsError::InvalidEntry(_))
+        ));
+        assert!(matches!(
+            parse_weights("\"PAWN_CORR\": 71", true),
+            Err(WeightsError::InvalidEntry(_))
+        ));
+    }
+
+    #[test]
+    fn weights_errors() {
+        assert!(matches!(
+            load_str("range.txt", "PAWN_CORR 500\n"),
+            Err(WeightsError::OutOfRange("PAWN_CORR", 500, 32, 96))
+        ));
+        assert!(matches!(
+            load_str("duplicate.txt", "PAWN_CORR 71\npawn_corr 71\n"),
+            Err(WeightsError::DuplicateWeight(name)) if name == "pawn_corr"
+        ));
+        assert!(matches!(
+            load_str("unknown.json", "{\"NOT_A_WEIGHT\": 1}"),
+            Err(WeightsError::UnknownWeight(name)) if name == "NOT_A_WEIGHT"
+        ));
+
+        #[cfg(not(feature = "tune"))]
+        assert!(matches!(
+            load_str("changed.txt", "PAWN_CORR 72\n"),
+            Err(WeightsError::NotTunable)
+        ));
+    }
+}
@@ -0,0 +1,348 @@
+use std::{fmt::Write as _, fs, path::Path};
+
+use rand::{RngExt, SeedableRng, rngs::SmallRng};
+
+use crate::*;
+
+/*----------------------------------------------------------------*/
+
+pub const DEFAULT_SPSA_TC: TimeControl = TimeControl {
+    base: 5000,
+    inc: 50,
+};
+
+const SPSA_ALPHA: f64 = 0.602;
+const SPSA_GAMMA: f64 = 0.101;
+
+#[derive(Debug, Clone)]
+pub struct SpsaOptions {
+    pub iterations: usize,
+    pub pairs: usize,
+    pub time: Option<TimeControl>,
+    pub nodes: Option<u64>,
+    pub openings: Option<String>,
+    pub moves: usize,
+    pub dfrc: bool,
+    pub seed: u64,
+    pub learning_rate: f64,
+    pub checkpoint: String,
+    pub output: String,
+}
+
+impl Default for SpsaOptions {
+    #[inline]
+    fn default() -> Self {
+        SpsaOptions {
+            iterations: 0,
+            pairs: 1,
+            time: None,
+            nodes: None,
+            openings: None,
+            moves: 8,
+            dfrc: false,
+            seed: 0,
+            learning_rate: 0.002,
+            checkpoint: String::from("spsa.txt"),
+            output: String::from("weights.rs"),
+        }
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+/*
+SPSA with the same schedule as OpenBench:
+`c_end` is the perturbation size and `r_end` the learning rate at the last iteration,
+`c_k = c / k^gamma` and `a_k = a / (A + k)^alpha` with `A` being 10% of the iterations.
+Each iteration plays game pairs between theta + c_k * delta and theta - c_k * delta,
+then moves theta by `a_k / c_k * (wins - losses) * delta`.
+*/
+struct Spsa {
+    theta: Vec<f64>,
+    c: Vec<f64>,
+    a: Vec<f64>,
+    big_a: f64,
+}
+
+impl Spsa {
+    fn new(theta: Vec<f64>, iterations: usize, learning_rate: f64) -> Spsa {
+        let iterations = iterations as f64;
+        let big_a = 0.1 * iterations;
+        let a_scale = learning_rate * (big_a + iterations).powf(SPSA_ALPHA);
+        let c_end = W::PARAMS
+            .iter()
+            .map(|param| (param.max - param.min).abs() as f64 / 25.0)
+            .collect::<Vec<_>>();
+
+        Spsa {
+            theta,
+            c: c_end
+                .iter()
+                .map(|&c_end| c_end * iterations.powf(SPSA_GAMMA))
+                .collect(),
+            a: c_end.iter().map(|&c_end| a_scale * c_end * c_end).collect(),
+            big_a,
+        }
+    }
+
+    #[inline]
+    fn clamp(value: f64, param: &WeightParam) -> f64 {
+        value.clamp(
+            param.min.min(param.max) as f64,
+            param.max.max(param.min) as f64,
+        )
+    }
+
+    fn perturb(&self, k: usize, delta: &[f64]) -> (Vec<i64>, Vec<i64>) {
+        let c_k = |i: usize| self.c[i] / (k as f64).powf(SPSA_GAMMA);
+        let variant = |sign: f64| {
+            W::PARAMS
+                .iter()
+                .enumerate()
+                .map(|(i, param)| {
+                    Spsa::clamp(self.theta[i] + sign * c_k(i) * delta[i], param).round() as i64
+                })
+                .collect::<Vec<_>>()
+        };
+
+        (variant(1.0), variant(-1.0))
+    }
+
+    fn update(&mut self, k: usize, delta: &[f64], result: i64) {
+        for (i, param) in W::PARAMS.iter().enumerate() {
+            let c_k = self.c[i] / (k as f64).powf(SPSA_GAMMA);
+            let a_k = self.a[i] / (self.big_a + k as f64).powf(SPSA_ALPHA);
+
+            self.theta[i] =
+                Spsa::clamp(self.theta[i] + a_k / c_k * result as f64 * delta[i], param);
+        }
+    }
+
+    #[inline]
+    fn values(&self) -> Vec<i64> {
+        self.theta
+            .iter()
+            .map(|value| value.round() as i64)
+            .collect()
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+/*
+Checkpoint Format:
+- `iteration <completed iterations>`
+- `<TUNABLE> <value>` for every weight
+*/
+fn save_checkpoint(path: &str, iteration: usize, theta: &[f64]) -> Result<(), String> {
+    let mut contents = format!("iteration {iteration}\n");
+    for (param, value) in W::PARAMS.iter().zip(theta) {
+        writeln!(contents, "{} {value}", param.tunable).unwrap();
+    }
+
+    fs::write(path, contents).map_err(|e| format!("Failed to write `{path}`: {e}"))
+}
+
+fn load_checkpoint(path: &str, iterations: usize) -> Result<(usize, Vec<f64>), String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read `{path}`: {e}"))?;
+    let mut iteration = None;
+    let mut theta = W::snapshot()
+        .into_iter()
+        .map(|value| value as f64)
+        .collect::<Vec<_>>();
+
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        let invalid = || format!("Invalid line in `{path}`: `{line}`");
+        let (name, value) = line.trim().split_once(' ').ok_or_else(invalid)?;
+        if name == "iteration" {
+            iteration = Some(value.trim().parse::<usize>().map_err(|_| invalid())?);
+            continue;
+        }
+
+        let index = W::PARAMS
+            .iter()
+            .position(|param| param.tunable == name)
+            .ok_or_else(|| format!("Unknown weight in `{path}`: `{name}`"))?;
+        let value = value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .ok_or_else(invalid)?;
+        theta[index] = Spsa::clamp(value, &W::PARAMS[index]);
+    }
+
+    let iteration = iteration.ok_or_else(|| format!("Missing iteration in `{path}`"))?;
+    if iteration > iterations {
+        return Err(format!(
+            "Checkpoint `{path}` is at iteration {iteration}, past the {iterations} iterations of this run"
+        ));
+    }
+
+    Ok((iteration, theta))
+}
+
+pub fn weights_block(values: &[i64]) -> String {
+    let width = |f: fn(&WeightParam) -> usize| W::PARAMS.iter().map(f).max().unwrap_or(0);
+    let name_width = width(|param| param.name.len());
+    let tunable_width = width(|param| param.tunable.len() + 1);
+    let value_width = values
+        .iter()
+        .map(|value| value.to_string().len())
+        .max()
+        .unwrap_or(0);
+
+    let mut block = String::from("weights! {\n");
+    for (param, value) in W::PARAMS.iter().zip(values) {
+        writeln!(
+            block,
+            "    {:<name_width$} | {:<tunable_width$} {} => {:<value_width$} | {}..={};",
+            param.name,
+            format!("{}:", param.tunable),
+            param.ty,
+            value,
+            param.min,
+            param.max,
+        )
+        .unwrap();
+    }
+    block.push_str("}\n");
+
+    block
+}
+
+/*----------------------------------------------------------------*/
+
+impl Engine {
+    pub fn spsa(&mut self, options: SpsaOptions) {
+        let openings = match &options.openings {
+            Some(path) => match load_openings(path) {
+                Ok(openings) if openings.is_empty() => {
+                    self.message(format!("No openings found in `{path}`"));
+                    return;
+                }
+                Ok(openings) => openings,
+                Err(e) => {
+                    self.message(e);
+                    return;
+                }
+            },
+            None => Vec::new(),
+        };
+
+        let base_weights = W::snapshot();
+        let (start, theta) = if Path::new(&options.checkpoint).exists() {
+            match load_checkpoint(&options.checkpoint, options.iterations) {
+                Ok((iteration, theta)) => {
+                    self.message(format!(
+                        "Resuming SPSA from `{}` at iteration {iteration}",
+                        options.checkpoint
+                    ));
+                    (iteration, theta)
+                }
+                Err(e) => {
+                    self.message(e);
+                    return;
+                }
+            }
+        } else {
+            (0, base_weights.iter().map(|&value| value as f64).collect())
+        };
+
+        let mut plus = match MatchPlayer::new(&[]) {
+            Ok(player) => player,
+            Err(e) => {
+                self.message(e);
+                return;
+            }
+        };
+        let mut minus = match MatchPlayer::new(&[]) {
+            Ok(player) => player,
+            Err(e) => {
+                self.message(e);
+                plus.quit();
+                return;
+            }
+        };
+
+        plus.options.frc |= options.dfrc;
+        minus.options.frc |= options.dfrc;
+
+        let settings = GameSettings {
+            time: options
+                .time
+                .or(options.nodes.is_none().then_some(DEFAULT_SPSA_TC)),
+            nodes: options.nodes,
+        };
+        let mut spsa = Spsa::new(theta, options.iterations, options.learning_rate);
+
+        for k in start + 1..=options.iterations {
+            //every iteration gets its own RNG so that resumed runs continue the same way
+            let mut rng =
+                SmallRng::seed_from_u64(options.seed ^ (k as u64).wrapping_mul(0x9E3779B97F4A7C15));
+            let delta = W::PARAMS
+                .iter()
+                .map(|_| if rng.random_bool(0.5) { 1.0 } else { -1.0 })
+                .collect::<Vec<_>>();
+            (plus.weights, minus.weights) = spsa.perturb(k, &delta);
+
+            let mut stats = MatchStats::default();
+            for _ in 0..options.pairs {
+                let opening = if openings.is_empty() {
+                    loop {
+                        if let Some(board) = gen_opening(&mut rng, options.dfrc, options.moves) {
+                            break board;
+                        }
+                    }
+                } else {
+                    openings[rng.random_range(0..openings.len())].clone()
+                };
+
+                stats.add(
+                    play_game(&mut plus, &mut minus, &opening, settings),
+                    Color::White,
+                );
+                stats.add(
+                    play_game(&mut minus, &mut plus, &opening, settings),
+                    Color::Black,
+                );
+            }
+
+            let result = stats.wins as i64 - stats.losses as i64;
+            spsa.update(k, &delta, result);
+
+            if let Err(e) = save_checkpoint(&options.checkpoint, k, &spsa.theta) {
+                self.message(e);
+            }
+
+            if self.options.json {
+                let mut output = JsonObject::new("spsa");
+                output
+                    .num("iteration", k)
+                    .num("iterations", options.iterations)
+                    .num("wins", stats.wins)
+                    .num("draws", stats.draws)
+                    .num("losses", stats.losses);
+
+                println!("{output}");
+            } else {
+                println!(
+                    "info string spsa iteration {k}/{} W {} D {} L {}",
+                    options.iterations, stats.wins, stats.draws, stats.losses
+                );
+            }
+        }
+
+        plus.quit();
+        minus.quit();
+        W::restore(&base_weights);
+
+        match fs::write(&options.output, weights_block(&spsa.values())) {
+            Ok(()) => self.message(format!(
+                "SPSA finished, wrote weights to `{}`",
+                options.output
+            )),
+            Err(e) => self.message(format!("Failed to write `{}`: {e}", options.output)),
+        }
+    }
+}
@@ -0,0 +1,664 @@
+use std::{fs, num::ParseFloatError, str::FromStr, time::*};
+
+use pyrrhic_rs::WdlProbeResult;
+use rand::{RngExt, SeedableRng, rngs::SmallRng};
+
+use crate::*;
+
+/*----------------------------------------------------------------*/
+
+pub const DEFAULT_MATCH_TC: TimeControl = TimeControl {
+    base: 8000,
+    inc: 80,
+};
+
+const RESIGN_SCORE: i32 = 400;
+const RESIGN_PLIES: u16 = 6;
+const DRAW_ADJ_SCORE: i32 = 10;
+const DRAW_ADJ_PLIES: u16 = 16;
+const DRAW_ADJ_MIN_PLY: u16 = 80;
+
+/*----------------------------------------------------------------*/
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TimeControl {
+    pub base: u64,
+    pub inc: u64,
+}
+
+impl FromStr for TimeControl {
+    type Err = ParseFloatError;
+
+    //base time and increment in seconds, e.g. `8+0.08`
+    fn from_str(s: &str) -> Result<TimeControl, ParseFloatError> {
+        let (base, inc) = s.split_once('+').unwrap_or((s, "0"));
+        let to_millis = |secs: &str| secs.parse::<f64>().map(|secs| (secs * 1000.0) as u64);
+
+        Ok(TimeControl {
+            base: to_millis(base)?,
+            inc: to_millis(inc)?,
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct GameSettings {
+    pub time: Option<TimeControl>,
+    pub nodes: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchOptions {
+    pub games: usize,
+    pub time: Option<TimeControl>,
+    pub nodes: Option<u64>,
+    pub openings: Option<String>,
+    pub moves: usize,
+    pub dfrc: bool,
+    pub seed: u64,
+    pub sprt: Sprt,
+    pub sprt_stop: bool,
+    pub first: Vec<(String, String)>,
+    pub second: Vec<(String, String)>,
+}
+
+impl Default for MatchOptions {
+    #[inline]
+    fn default() -> Self {
+        MatchOptions {
+            games: 0,
+            time: None,
+            nodes: None,
+            openings: None,
+            moves: 8,
+            dfrc: false,
+            seed: 0,
+            sprt: Sprt::default(),
+            sprt_stop: true,
+            first: Vec::new(),
+            second: Vec::new(),
+        }
+    }
+}
+
+impl MatchOptions {
+    #[inline]
+    pub fn settings(&self) -> GameSettings {
+        GameSettings {
+            time: self
+                .time
+                .or(self.nodes.is_none().then_some(DEFAULT_MATCH_TC)),
+            nodes: self.nodes,
+        }
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct MatchStats {
+    pub wins: u64,
+    pub draws: u64,
+    pub losses: u64,
+    //Colour-swapped game pairs, indexed by the pair score in half points
+    pub pairs: [u64; 5],
+}
+
+impl MatchStats {
+    //Score of the player with `color` in half points
+    #[inline]
+    pub fn half_points(result: GameResult, color: Color) -> usize {
+        match (result, color) {
+            (GameResult::WhiteWin, Color::White) | (GameResult::BlackWin, Color::Black) => 2,
+            (GameResult::WhiteWin, Color::Black) | (GameResult::BlackWin, Color::White) => 0,
+            _ => 1,
+        }
+    }
+
+    #[inline]
+    pub fn add(&mut self, result: GameResult, color: Color) {
+        match MatchStats::half_points(result, color) {
+            2 => self.wins += 1,
+            0 => self.losses += 1,
+            _ => self.draws += 1,
+        }
+    }
+
+    #[inline]
+    pub fn add_pair(&mut self, half_points: usize) {
+        self.pairs[half_points] += 1;
+    }
+
+    #[inline]
+    pub fn games(&self) -> u64 {
+        self.wins + self.draws + self.losses
+    }
+
+    #[inline]
+    pub fn num_pairs(&self) -> u64 {
+        self.pairs.iter().sum()
+    }
+
+    /*
+    Mean score per game and its variance,
+    using the trinomial model with each game as an independent sample.
+    */
+    #[inline]
+    pub fn score(&self) -> (f64, f64) {
+        let games = self.games().max(1) as f64;
+        let (w, d, l) = (
+            self.wins as f64 / games,
+            self.draws as f64 / games,
+            self.losses as f64 / games,
+        );
+        let score = w + d / 2.0;
+        let variance =
+            w * (1.0 - score).powi(2) + d * (0.5 - score).powi(2) + l * (0.0 - score).powi(2);
+
+        (score, variance)
+    }
+
+    /*
+    Mean score per game and its variance,
+    using the pentanomial model with each game pair as an independent sample.
+    */
+    #[inline]
+    pub fn pair_score(&self) -> (f64, f64) {
+        let pairs = self.num_pairs().max(1) as f64;
+        let probability = |i: usize| self.pairs[i] as f64 / pairs;
+        let score = (0..5).map(|i| probability(i) * i as f64 / 4.0).sum::<f64>();
+        let variance = (0..5)
+            .map(|i| probability(i) * (i as f64 / 4.0 - score).powi(2))
+            .sum::<f64>();
+
+        (score, variance)
+    }
+
+    /*
+    Logistic Elo difference and the half-width of its 95% confidence interval,
+    using the same pentanomial model as the SPRT once there are complete game pairs.
+    */
+    pub fn elo(&self) -> (f64, f64) {
+        let ((score, variance), samples) = match self.num_pairs() {
+            0 => (self.score(), self.games()),
+            pairs => (self.pair_score(), pairs),
+        };
+        let error = 1.959964 * (variance / samples.max(1) as f64).sqrt();
+        let lower = score_to_elo(score - error);
+        let upper = score_to_elo(score + error);
+
+        (score_to_elo(score), (upper - lower) / 2.0)
+    }
+}
+
+#[inline]
+fn score_to_elo(score: f64) -> f64 {
+    let score = score.clamp(1e-6, 1.0 - 1e-6);
+    -400.0 * (1.0 / score - 1.0).log10() + 0.0
+}
+
+#[inline]
+fn elo_to_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/*----------------------------------------------------------------*/
+
+#[derive(Debug, Copy, Clone)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Default for Sprt {
+    #[inline]
+    fn default() -> Self {
+        Sprt {
+            elo0: 0.0,
+            elo1: 3.0,
+            alpha: 0.05,
+            beta: 0.1,
+        }
+    }
+}
+
+impl Sprt {
+    #[inline]
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /*
+    Generalized SPRT log-likelihood ratio of H1 (elo1) against H0 (elo0),
+    approximating the distribution of pair scores as normal with the observed variance.
+    */
+    pub fn llr(&self, stats: &MatchStats) -> f64 {
+        let pairs = stats.num_pairs();
+        let (score, variance) = stats.pair_score();
+        if pairs == 0 || variance <= 0.0 {
+            return 0.0;
+        }
+
+        let s0 = elo_to_score(self.elo0);
+        let s1 = elo_to_score(self.elo1);
+
+        pairs as f64 * (s1 - s0) * (2.0 * score - s0 - s1) / (2.0 * variance)
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+const GLOBAL_OPTIONS: [&str; 2] = ["EvalFile", "SyzygyPath"];
+
+/*
+One configuration of Cherry taking part in a match.
+With the `tune` feature, weights are global, so every player keeps its own copy
+and swaps it in before searching. Games are played one at a time for that reason.
+*/
+pub struct MatchPlayer {
+    pub searcher: Searcher,
+    pub options: EngineOptions,
+    #[cfg(feature = "tune")]
+    pub weights: Vec<i64>,
+}
+
+impl MatchPlayer {
+    pub fn new(options: &[(String, String)]) -> Result<MatchPlayer, OptionError> {
+        #[cfg(feature = "tune")]
+        let base_weights = W::snapshot();
+
+        let mut engine = Engine::new();
+        let result = options.iter().try_for_each(|(name, value)| {
+            //these load process-wide state, so they would silently apply to both players
+            if let Some(&global) = GLOBAL_OPTIONS
+                .iter()
+                .find(|global| global.eq_ignore_ascii_case(name))
+            {
+                return Err(OptionError::Global(global));
+            }
+
+            engine.set_option(name, value).map(|_| ())
+        });
+
+        #[cfg(feature = "tune")]
+        let weights = W::snapshot();
+        #[cfg(feature = "tune")]
+        W::restore(&base_weights);
+
+        let Engine {
+            mut searcher,
+            options,
+            ..
+        } = engine;
+
+        if let Err(e) = result {
+            searcher.quit();
+            return Err(e);
+        }
+
+        Ok(MatchPlayer {
+            searcher,
+            options,
+            #[cfg(feature = "tune")]
+            weights,
+        })
+    }
+
+    #[inline]
+    fn search(&mut self, pos: &Position, limits: Vec<SearchLimit>) -> (Move, Score) {
+        #[cfg(feature = "tune")]
+        W::restore(&self.weights);
+
+        self.searcher
+            .search(pos.clone(), limits, self.options, SearchInfo::none());
+        self.searcher.wait();
+
+        (
+            self.searcher.shared.best_move().unwrap(),
+            self.searcher.shared.best_score(),
+        )
+    }
+
+    #[inline]
+    pub fn quit(&mut self) {
+        self.searcher.quit();
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+pub fn play_game(
+    white: &mut MatchPlayer,
+    black: &mut MatchPlayer,
+    opening: &Board,
+    settings: GameSettings,
+) -> GameResult {
+    let win_for = |color: Color| match color {
+        Color::White => GameResult::WhiteWin,
+        Color::Black => GameResult::BlackWin,
+    };
+
+    white.searcher.newgame();
+    black.searcher.newgame();
+
+    let mut pos = Position::new(opening.clone());
+    let mut clock = [settings.time.map_or(0, |tc| tc.base); Color::COUNT];
+    let mut resign: Option<(Color, u16)> = None;
+    let mut draw_plies = 0u16;
+    let mut ply = 0u16;
+
+    loop {
+        let board = pos.board().clone();
+        let stm = board.stm();
+
+        match board.status() {
+            BoardStatus::Checkmate => return win_for(!stm),
+            BoardStatus::Draw => return GameResult::Draw,
+            BoardStatus::Ongoing => {}
+        }
+
+        if pos.is_draw() {
+            return GameResult::Draw;
+        }
+
+        //same conditions as in search, WDL tables are only exact right after a zeroing move
+        if board.halfmove_clock() == 0
+            && board.castle_rights(Color::White).is_none()
+            && board.castle_rights(Color::Black).is_none()
+            && let Some(wdl) = probe_wdl(&board)
+        {
+            return match wdl {
+                WdlProbeResult::Win => win_for(stm),
+                WdlProbeResult::Loss => win_for(!stm),
+                //the 50-move rule turns these into draws
+                WdlProbeResult::CursedWin | WdlProbeResult::BlessedLoss => GameResult::Draw,
+                WdlProbeResult::Draw => GameResult::Draw,
+            };
+        }
+
+        let mut limits = Vec::new();
+        if let Some(nodes) = settings.nodes {
+            limits.push(SearchLimit::Nodes(nodes));
+        }
+
+        if let Some(tc) = settings.time {
+            limits.extend([
+                SearchLimit::WhiteTime(clock[Color::White]),
+                SearchLimit::BlackTime(clock[Color::Black]),
+                SearchLimit::WhiteInc(tc.inc),
+                SearchLimit::BlackInc(tc.inc),
+            ]);
+        }
+
+        let player = match stm {
+            Color::White => &mut *white,
+            Color::Black => &mut *black,
+        };
+
+        let time = Instant::now();
+        let (mv, score) = player.search(&pos, limits);
+
+        if let Some(tc) = settings.time {
+            let elapsed = time.elapsed().as_millis() as u64;
+            if elapsed > clock[stm] {
+                return win_for(!stm);
+            }
+
+            clock[stm] = clock[stm] - elapsed + tc.inc;
+        }
+
+        //both players have to agree on the winner for a few moves before resigning
+        if score.0.abs() >= RESIGN_SCORE {
+            let winner = if score > Score::ZERO { stm } else { !stm };
+            resign = match resign {
+                Some((color, plies)) if color == winner => Some((color, plies + 1)),
+                _ => Some((winner, 1)),
+            };
+        } else {
+            resign = None;
+        }
+
+        if let Some((winner, plies)) = resign
+            && plies >= RESIGN_PLIES
+        {
+            return win_for(winner);
+        }
+
+        if ply >= DRAW_ADJ_MIN_PLY && score.0.abs() <= DRAW_ADJ_SCORE {
+            draw_plies += 1;
+        } else {
+            draw_plies = 0;
+        }
+
+        if draw_plies >= DRAW_ADJ_PLIES {
+            return GameResult::Draw;
+        }
+
+        pos.make_move(mv);
+        pos.reset_nnue();
+        ply += 1;
+    }
+}
+
+//Openings can be given as FEN or as EPD, in which case the clocks are reset
+pub fn load_openings(path: &str) -> Result<Vec<Board>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read `{path}`: {e}"))?;
+
+    Ok(contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            Board::from_fen(line).ok().or_else(|| {
+                let fields = line.split_ascii_whitespace().take(4).collect::<Vec<_>>();
+                Board::from_fen(&format!("{} 0 1", fields.join(" "))).ok()
+            })
+        })
+        .collect())
+}
+
+/*----------------------------------------------------------------*/
+
+impl Engine {
+    pub fn run_match(&mut self, options: MatchOptions) {
+        let openings = match &options.openings {
+            Some(path) => match load_openings(path) {
+                Ok(openings) if openings.is_empty() => {
+                    self.message(format!("No openings found in `{path}`"));
+                    return;
+                }
+                Ok(openings) => openings,
+                Err(e) => {
+                    self.message(e);
+                    return;
+                }
+            },
+            None => Vec::new(),
+        };
+
+        let mut first = match MatchPlayer::new(&options.first) {
+            Ok(player) => player,
+            Err(e) => {
+                self.message(e);
+                return;
+            }
+        };
+        let mut second = match MatchPlayer::new(&options.second) {
+            Ok(player) => player,
+            Err(e) => {
+                self.message(e);
+                first.quit();
+                return;
+            }
+        };
+
+        first.options.frc |= options.dfrc;
+        second.options.frc |= options.dfrc;
+
+        #[cfg(feature = "tune")]
+        let base_weights = W::snapshot();
+
+        let settings = options.settings();
+        let (lower, upper) = options.sprt.bounds();
+        let mut rng = SmallRng::seed_from_u64(options.seed);
+        let mut stats = MatchStats::default();
+        let mut games = 0;
+        let mut llr = 0.0;
+
+        while games < options.games {
+            let opening = if openings.is_empty() {
+                loop {
+                    if let Some(board) = gen_opening(&mut rng, options.dfrc, options.moves) {
+                        break board;
+                    }
+                }
+            } else {
+                openings[rng.random_range(0..openings.len())].clone()
+            };
+
+            //every opening is played twice, with swapped colours
+            let mut half_points = 0;
+            let mut played = 0;
+            for color in Color::ALL {
+                if games >= options.games {
+                    break;
+                }
+
+                let result = match color {
+                    Color::White => play_game(&mut first, &mut second, &opening, settings),
+                    Color::Black => play_game(&mut second, &mut first, &opening, settings),
+                };
+
+                stats.add(result, color);
+                half_points += MatchStats::half_points(result, color);
+                played += 1;
+                games += 1;
+            }
+
+            if played == 2 {
+                stats.add_pair(half_points);
+            }
+
+            llr = options.sprt.llr(&stats);
+            self.report_match(&stats, llr, (lower, upper));
+
+            if options.sprt_stop && (llr <= lower || llr >= upper) {
+                break;
+            }
+        }
+
+        let result = if llr >= upper {
+            "H1 accepted"
+        } else if llr <= lower {
+            "H0 accepted"
+        } else {
+            "inconclusive"
+        };
+
+        if self.options.json {
+            let mut output = JsonObject::new("match_result");
+            output.str("result", result);
+
+            println!("{output}");
+        } else {
+            println!("info string match finished, SPRT {result}");
+        }
+
+        first.quit();
+        second.quit();
+
+        #[cfg(feature = "tune")]
+        W::restore(&base_weights);
+    }
+
+    fn report_match(&self, stats: &MatchStats, llr: f64, (lower, upper): (f64, f64)) {
+        let (elo, error) = stats.elo();
+
+        if self.options.json {
+            let mut output = JsonObject::new("match");
+            output
+                .num("games", stats.games())
+                .num("wins", stats.wins)
+                .num("draws", stats.draws)
+                .num("losses", stats.losses)
+                .num("elo", format!("{elo:.2}"))
+                .num("elo_error", format!("{error:.2}"))
+                .num("llr", format!("{llr:.2}"))
+                .num_array("bounds", [format!("{lower:.2}"), format!("{upper:.2}")]);
+
+            println!("{output}");
+            return;
+        }
+
+        println!(
+            "info string match games {} W {} D {} L {} elo {elo:.2} +- {error:.2} llr {llr:.2} ({lower:.2}, {upper:.2})",
+            stats.games(),
+            stats.wins,
+            stats.draws,
+            stats.losses,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn elo_conversion() {
+        assert_close(score_to_elo(0.5), 0.0);
+        assert_close(score_to_elo(0.75), 190.848502);
+        assert_close(elo_to_score(100.0), 0.640065);
+        assert_close(score_to_elo(elo_to_score(-250.0)), -250.0);
+    }
+
+    #[test]
+    fn match_elo() {
+        let stats = MatchStats {
+            wins: 30,
+            draws: 50,
+            losses: 20,
+            ..MatchStats::default()
+        };
+        let (elo, error) = stats.elo();
+
+        assert_close(elo, 34.860070);
+        assert_close(error, 48.470228);
+
+        //the same games as colour-swapped pairs, with the pentanomial error
+        let stats = MatchStats {
+            pairs: [0, 10, 20, 20, 0],
+            ..stats
+        };
+        let (elo, error) = stats.elo();
+
+        assert_close(elo, 34.860070);
+        assert_close(error, 36.535166);
+    }
+
+    #[test]
+    fn sprt_llr() {
+        let sprt = Sprt::default();
+        let (lower, upper) = sprt.bounds();
+        assert_close(lower, -2.251292);
+        assert_close(upper, 2.890372);
+
+        let mut stats = MatchStats::default();
+        assert_eq!(sprt.llr(&stats), 0.0);
+
+        for (half_points, count) in [(1, 10), (2, 20), (3, 20)] {
+            for _ in 0..count {
+                stats.add_pair(half_points);
+            }
+        }
+        assert_close(sprt.llr(&stats), 0.295061);
+    }
+}
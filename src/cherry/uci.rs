@@ -1,6 +1,6 @@
 use std::{
     iter::Peekable,
-    num::ParseIntError,
+    num::{ParseFloatError, ParseIntError},
     str::{FromStr, ParseBoolError, SplitAsciiWhitespace},
 };
 
@@ -28,22 +28,52 @@ pub enum UciCommand {
         depth: u8,
         bulk: bool,
     },
+    PerftSuite(String),
     Bench {
         depth: u8,
     },
+    Epd {
+        path: String,
+        limits: Vec<SearchLimit>,
+    },
     GenFens {
         num: usize,
         seed: u64,
         dfrc: bool,
         moves: usize,
     },
+    Datagen(DatagenOptions),
+    Match(MatchOptions),
+    SaveHash(String),
+    Book,
+    MakeBook {
+        pgn: String,
+        output: String,
+        plies: usize,
+    },
+    LoadHash(String),
+    SaveWeights(String),
+    LoadWeights(String),
+    FitWdl {
+        path: String,
+        apply: bool,
+    },
+    Analyze {
+        pgn: String,
+        limits: Vec<SearchLimit>,
+        output: String,
+    },
     SetOption {
         name: String,
         value: String,
     },
     #[cfg(feature = "tune")]
     Spsa,
+    #[cfg(feature = "tune")]
+    SpsaTune(SpsaOptions),
+    RootMoves,
     Wait,
+    PonderHit,
     Stop,
     Quit,
 }
@@ -62,8 +92,8 @@ pub enum UciParseError {
     MissingScharnagl,
     #[error("Invalid Scharnagl Number: `{0}`")]
     InvalidScharnagl(u16),
-    #[error("Invalid FEN: `{0}`")]
-    InvalidFen(String),
+    #[error("Invalid FEN: `{0}` ({1})")]
+    InvalidFen(String, FenError),
     #[error("Invalid Move: `{0}`")]
     InvalidMove(String),
     #[error("Missing position type (e.g. `startpos`, `fen`) in `position` command")]
@@ -78,6 +108,10 @@ pub enum UciParseError {
     MissingPerftDepth,
     #[error("Missing bulk option in `perft` or `splitperft` command")]
     MissingPerftBulk,
+    #[error("Missing file path in `perftsuite` command")]
+    MissingPerftSuitePath,
+    #[error("Missing file path in `epd` command")]
+    MissingEpdPath,
     #[error("Missing Number of Fens")]
     MissingGenFensNumber,
     #[error("Missing `seed` token in `genfens` command")]
@@ -96,18 +130,46 @@ pub enum UciParseError {
     MissingGenFensDfrcValue,
     #[error("Missing `moves` value in `genfens` command")]
     MissingGenFensMovesValue,
+    #[error("Missing Number of Games in `datagen` command")]
+    MissingDatagenGames,
+    #[error("Unknown option in `datagen` command: `{0}`")]
+    UnknownDatagenOption(String),
+    #[error("Missing value for option in `datagen` command: `{0}`")]
+    MissingDatagenValue(String),
+    #[error("Missing Number of Games in `match` command")]
+    MissingMatchGames,
+    #[error("Unknown option in `match` command: `{0}`")]
+    UnknownMatchOption(String),
+    #[error("Missing value for option in `match` command: `{0}`")]
+    MissingMatchValue(String),
+    #[error("Unknown option in `spsa` command: `{0}`")]
+    UnknownSpsaOption(String),
+    #[error("Missing value for option in `spsa` command: `{0}`")]
+    MissingSpsaValue(String),
+    #[error("Invalid Time Control: `{0}`")]
+    InvalidTimeControl(String),
+    #[error("Missing PGN file in `makebook` command")]
+    MissingMakeBookPgn,
+    #[error("Missing output file in `makebook` command")]
+    MissingMakeBookOutput,
+    #[error("Missing file path in `savehash` or `loadhash` command")]
+    MissingHashFile,
+    #[error("Missing file path in `saveweights` or `loadweights` command")]
+    MissingWeightsFile,
+    #[error("Missing file path in `fitwdl` command")]
+    MissingFitWdlPath,
+    #[error("Missing file path in `analyze` command")]
+    MissingAnalyzePath,
     #[error("Missing `name` token in `setoption` command")]
     MissingOptionNameToken,
-    #[error("Missing `value` token in `setoption` command")]
-    MissingOptionValueToken,
     #[error("Missing option name in `setoption` command")]
     MissingOptionName,
-    #[error("Missing option value in `setoption` command")]
-    MissingOptionValue,
     #[error("Error parsing integer: `{0}`")]
     InvalidInteger(#[from] ParseIntError),
     #[error("Error parsing boolean: `{0}`")]
     InvalidBoolean(#[from] ParseBoolError),
+    #[error("Error parsing float: `{0}`")]
+    InvalidFloat(#[from] ParseFloatError),
 }
 
 /*----------------------------------------------------------------*/
@@ -127,8 +189,46 @@ impl UciCommand {
             "eval" => Ok(Eval),
             "display" | "d" => Ok(Display),
             #[cfg(feature = "tune")]
-            "spsa" => Ok(Spsa),
+            "spsa" => {
+                let Some(iterations) = reader.next() else {
+                    return Ok(Spsa);
+                };
+
+                let mut options = SpsaOptions {
+                    iterations: iterations.parse::<usize>()?,
+                    ..SpsaOptions::default()
+                };
+
+                while let Some(token) = reader.next() {
+                    let value = reader
+                        .next()
+                        .ok_or_else(|| MissingSpsaValue(token.to_string()))?;
+
+                    match token {
+                        "pairs" => options.pairs = value.parse::<usize>()?.max(1),
+                        "tc" =>
+                            options.time = Some(
+                                value
+                                    .parse()
+                                    .map_err(|_| InvalidTimeControl(value.to_string()))?,
+                            ),
+                        "nodes" => options.nodes = Some(value.parse()?),
+                        "openings" => options.openings = Some(value.to_string()),
+                        "moves" => options.moves = value.parse()?,
+                        "dfrc" => options.dfrc = value.parse()?,
+                        "seed" => options.seed = value.parse()?,
+                        "lr" => options.learning_rate = value.parse()?,
+                        "checkpoint" => options.checkpoint = value.to_string(),
+                        "output" => options.output = value.to_string(),
+                        _ => return Err(UnknownSpsaOption(token.to_string())),
+                    }
+                }
+
+                Ok(SpsaTune(options))
+            }
+            "rootmoves" => Ok(RootMoves),
             "wait" => Ok(Wait),
+            "ponderhit" => Ok(PonderHit),
             "stop" => Ok(Stop),
             "quit" | "q" => Ok(Quit),
             "position" => Self::parse_position(reader, frc),
@@ -145,11 +245,66 @@ impl UciCommand {
 
                 Ok(SplitPerft { depth, bulk })
             }
+            "perftsuite" => Ok(PerftSuite(
+                reader.next().ok_or(MissingPerftSuitePath)?.to_string(),
+            )),
             "bench" => {
                 let depth = reader.next().map_or(Ok(12), str::parse)?;
 
                 Ok(Bench { depth })
             }
+            "epd" => {
+                let path = reader.next().ok_or(MissingEpdPath)?.to_string();
+                let limit = match reader.next() {
+                    Some(token) => {
+                        let value = reader
+                            .next()
+                            .ok_or_else(|| MissingLimitValue(token.to_string()))?;
+
+                        match token {
+                            "movetime" => SearchLimit::MoveTime(value.parse()?),
+                            "nodes" => SearchLimit::Nodes(value.parse()?),
+                            "depth" => SearchLimit::Depth(value.parse()?),
+                            _ => return Err(UnknownLimit(token.to_string())),
+                        }
+                    }
+                    None => SearchLimit::MoveTime(1000),
+                };
+
+                Ok(Epd {
+                    path,
+                    limits: vec![limit],
+                })
+            }
+            "analyze" => {
+                let pgn = reader.next().ok_or(MissingAnalyzePath)?.to_string();
+                let mut limits = Vec::new();
+                let mut output = None;
+
+                while let Some(token) = reader.next() {
+                    let value = reader
+                        .next()
+                        .ok_or_else(|| MissingLimitValue(token.to_string()))?;
+
+                    match token {
+                        "movetime" => limits.push(SearchLimit::MoveTime(value.parse()?)),
+                        "nodes" => limits.push(SearchLimit::Nodes(value.parse()?)),
+                        "depth" => limits.push(SearchLimit::Depth(value.parse()?)),
+                        "output" => output = Some(value.to_string()),
+                        _ => return Err(UnknownLimit(token.to_string())),
+                    }
+                }
+
+                if limits.is_empty() {
+                    limits.push(SearchLimit::MoveTime(DEFAULT_ANALYZE_MOVETIME));
+                }
+
+                Ok(Analyze {
+                    output: output.unwrap_or_else(|| default_analysis_path(&pgn)),
+                    pgn,
+                    limits,
+                })
+            }
             "genfens" => {
                 let num = reader
                     .next()
@@ -192,17 +347,119 @@ impl UciCommand {
                     moves,
                 })
             }
+            "datagen" => {
+                let mut options = DatagenOptions {
+                    games: reader.next().ok_or(MissingDatagenGames)?.parse::<usize>()?,
+                    ..DatagenOptions::default()
+                };
+
+                while let Some(token) = reader.next() {
+                    let value = reader
+                        .next()
+                        .ok_or_else(|| MissingDatagenValue(token.to_string()))?;
+
+                    match token {
+                        "threads" => options.threads = value.parse::<usize>()?.max(1),
+                        "seed" => options.seed = value.parse()?,
+                        "nodes" => options.nodes = value.parse()?,
+                        "dfrc" => options.dfrc = value.parse()?,
+                        "moves" => options.moves = value.parse()?,
+                        "output" => options.output = value.to_string(),
+                        _ => return Err(UnknownDatagenOption(token.to_string())),
+                    }
+                }
+
+                Ok(Datagen(options))
+            }
+            "match" => {
+                let mut options = MatchOptions {
+                    games: reader.next().ok_or(MissingMatchGames)?.parse::<usize>()?,
+                    ..MatchOptions::default()
+                };
+
+                //engine options are given per player, e.g. `first.Hash 16` or `second.Threads 2`
+                while let Some(token) = reader.next() {
+                    let value = reader
+                        .next()
+                        .ok_or_else(|| MissingMatchValue(token.to_string()))?;
+
+                    if let Some(name) = token.strip_prefix("first.") {
+                        options.first.push((name.to_string(), value.to_string()));
+                        continue;
+                    }
+
+                    if let Some(name) = token.strip_prefix("second.") {
+                        options.second.push((name.to_string(), value.to_string()));
+                        continue;
+                    }
+
+                    match token {
+                        "tc" =>
+                            options.time = Some(
+                                value
+                                    .parse()
+                                    .map_err(|_| InvalidTimeControl(value.to_string()))?,
+                            ),
+                        "nodes" => options.nodes = Some(value.parse()?),
+                        "openings" => options.openings = Some(value.to_string()),
+                        "moves" => options.moves = value.parse()?,
+                        "dfrc" => options.dfrc = value.parse()?,
+                        "seed" => options.seed = value.parse()?,
+                        "elo0" => options.sprt.elo0 = value.parse()?,
+                        "elo1" => options.sprt.elo1 = value.parse()?,
+                        "alpha" => options.sprt.alpha = value.parse()?,
+                        "beta" => options.sprt.beta = value.parse()?,
+                        "sprt" => options.sprt_stop = value.parse()?,
+                        _ => return Err(UnknownMatchOption(token.to_string())),
+                    }
+                }
+
+                Ok(Match(options))
+            }
+            "book" => Ok(Book),
+            "makebook" => {
+                let pgn = reader.next().ok_or(MissingMakeBookPgn)?.to_string();
+                let output = reader.next().ok_or(MissingMakeBookOutput)?.to_string();
+                let plies = match reader.next() {
+                    Some(plies) => plies.parse::<usize>()?,
+                    None => DEFAULT_MAKEBOOK_PLIES,
+                };
+
+                Ok(MakeBook { pgn, output, plies })
+            }
+            "savehash" => Ok(SaveHash(reader.next().ok_or(MissingHashFile)?.to_string())),
+            "loadhash" => Ok(LoadHash(reader.next().ok_or(MissingHashFile)?.to_string())),
+            "saveweights" => Ok(SaveWeights(
+                reader.next().ok_or(MissingWeightsFile)?.to_string(),
+            )),
+            "loadweights" => Ok(LoadWeights(
+                reader.next().ok_or(MissingWeightsFile)?.to_string(),
+            )),
+            "fitwdl" => Ok(FitWdl {
+                path: reader.next().ok_or(MissingFitWdlPath)?.to_string(),
+                apply: reader.next() == Some("apply"),
+            }),
             "setoption" => {
                 if reader.next() != Some("name") {
                     return Err(MissingOptionNameToken);
                 }
 
-                let name = reader.next().ok_or(MissingOptionName)?.to_string();
-                if reader.next() != Some("value") {
-                    return Err(MissingOptionValueToken);
+                //option names and values may contain spaces, e.g. `Skill Level`
+                let mut name = Vec::new();
+                for token in reader.by_ref() {
+                    if token == "value" {
+                        break;
+                    }
+
+                    name.push(token);
+                }
+
+                if name.is_empty() {
+                    return Err(MissingOptionName);
                 }
 
-                let value = reader.next().ok_or(MissingOptionValue)?.to_string();
+                let name = name.join(" ");
+                let value = reader.remainder().unwrap_or("").trim().to_string();
                 Ok(SetOption { name, value })
             }
             _ => Err(UnknownCommand(cmd.to_string())),
@@ -261,7 +518,7 @@ impl UciCommand {
                     fen.push_str(part);
                 }
 
-                Board::from_fen(&fen).ok_or(InvalidFen(fen))?
+                Board::from_fen(&fen).map_err(|e| InvalidFen(fen, e))?
             }
             _ => return Err(MissingPositionType),
         };
@@ -306,7 +563,10 @@ impl UciCommand {
             "movestogo",
             "depth",
             "nodes",
+            "mate",
             "infinite",
+            "ponder",
+            "verbose",
         ];
 
         let mut reader = reader.peekable();
@@ -326,6 +586,8 @@ impl UciCommand {
         while let Some(token) = reader.next() {
             match token {
                 "infinite" => {}
+                "ponder" => limits.push(Ponder),
+                "verbose" => limits.push(Verbose),
                 "wtime" => limits.push(WhiteTime(
                     parse_int::<i64>(&mut reader, token)?.max(0) as u64
                 )),
@@ -338,6 +600,7 @@ impl UciCommand {
                 "movestogo" => limits.push(MovesToGo(parse_int(&mut reader, token)?)),
                 "depth" => limits.push(Depth(parse_int(&mut reader, token)?)),
                 "nodes" => limits.push(Nodes(parse_int(&mut reader, token)?)),
+                "mate" => limits.push(Mate(parse_int(&mut reader, token)?)),
                 "searchmoves" => {
                     let mut moves = MoveList::empty();
                     while let Some(token) = reader.peek()
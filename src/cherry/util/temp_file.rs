@@ -0,0 +1,26 @@
+use std::path::{Path, PathBuf};
+
+/*----------------------------------------------------------------*/
+
+/// A file in the temp dir that is removed on drop, even if the test using it panics.
+pub struct TempFile(PathBuf);
+
+impl TempFile {
+    #[inline]
+    pub fn new(name: &str) -> TempFile {
+        TempFile(std::env::temp_dir().join(format!("cherry-{}-{name}", std::process::id())))
+    }
+}
+
+impl AsRef<Path> for TempFile {
+    #[inline]
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
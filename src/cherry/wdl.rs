@@ -1,16 +1,81 @@
+use std::sync::RwLock;
+
 use crate::cherry::Score;
 
+/*----------------------------------------------------------------*/
+
+pub const WDL_MIN_MATERIAL: i16 = 17;
+pub const WDL_MAX_MATERIAL: i16 = 78;
+pub const WDL_REF_MATERIAL: i16 = 58;
+
+/*
+`a` is the score at which a win is 50% likely, `b` the spread of the curve.
+Both are cubic polynomials in the amount of material on the board,
+with the coefficients in order of descending degree.
+*/
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WdlModel {
+    pub a: [f64; 4],
+    pub b: [f64; 4],
+}
+
+impl WdlModel {
+    pub const DEFAULT: WdlModel = WdlModel {
+        a: [-90.46060983, 180.58434842, -183.30051524, 352.00571429],
+        b: [-13.25291718, 51.82458721, -11.76784060, 44.37897932],
+    };
+
+    #[inline]
+    pub fn material_scale(material: i16) -> f64 {
+        material.clamp(WDL_MIN_MATERIAL, WDL_MAX_MATERIAL) as f64 / WDL_REF_MATERIAL as f64
+    }
+
+    #[inline]
+    pub fn params(&self, material: i16) -> (f64, f64) {
+        let (a, b) = (self.a, self.b);
+        let m = WdlModel::material_scale(material);
+
+        (
+            ((a[0] * m + a[1]) * m + a[2]) * m + a[3],
+            ((b[0] * m + b[1]) * m + b[2]) * m + b[3],
+        )
+    }
+
+    #[inline]
+    pub fn probabilities(&self, score: f64, material: i16) -> (f64, f64) {
+        let (a, b) = self.params(material);
+
+        (
+            1.0 / (1.0 + f64::exp((a - score) / b)),
+            1.0 / (1.0 + f64::exp((a + score) / b)),
+        )
+    }
+}
+
+impl Default for WdlModel {
+    #[inline]
+    fn default() -> Self {
+        WdlModel::DEFAULT
+    }
+}
+
+static WDL_MODEL: RwLock<WdlModel> = RwLock::new(WdlModel::DEFAULT);
+
 #[inline]
-pub fn wdl_params(material: i16) -> (f64, f64) {
-    let a = [-90.46060983, 180.58434842, -183.30051524, 352.00571429];
-    let b = [-13.25291718, 51.82458721, -11.76784060, 44.37897932];
+pub fn current_wdl_model() -> WdlModel {
+    *WDL_MODEL.read().unwrap()
+}
 
-    let m = material.clamp(17, 78) as f64 / 58.0;
+#[inline]
+pub fn set_wdl_model(model: WdlModel) {
+    *WDL_MODEL.write().unwrap() = model;
+}
 
-    (
-        ((a[0] * m + a[1]) * m + a[2]) * m + a[3],
-        ((b[0] * m + b[1]) * m + b[2]) * m + b[3],
-    )
+/*----------------------------------------------------------------*/
+
+#[inline]
+pub fn wdl_params(material: i16) -> (f64, f64) {
+    current_wdl_model().params(material)
 }
 
 #[inline]
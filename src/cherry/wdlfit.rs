@@ -0,0 +1,554 @@
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use crate::*;
+
+/*----------------------------------------------------------------*/
+
+const WDL_FIT_MAX_SCORE: i32 = 2000;
+const WDL_FIT_MIN_POSITIONS: u64 = 64;
+const WDL_FIT_EPSILON: f64 = 1e-12;
+
+#[derive(thiserror::Error, Debug)]
+pub enum WdlFitError {
+    #[error("I/O Error: `{0}`")]
+    Io(#[from] io::Error),
+    #[error("Not a datagen file: size of {0} bytes is not a multiple of 32")]
+    InvalidSize(usize),
+    #[error("Failed to parse PGN: `{0}`")]
+    InvalidPgn(String),
+    #[error("Not enough data to fit the WDL model ({0} positions)")]
+    NotEnoughData(u64),
+}
+
+//(score, [loss, draw, win] counts) for every score seen with the given material
+type WdlGroup = (i16, Vec<(f64, [f64; 3])>);
+
+/*
+Positions are bucketed by material and score, with the results
+counted from the side to move's perspective, so the likelihood
+only has to be evaluated once per distinct bucket.
+
+The scores have to be raw search scores, so `[%cp_raw]` is preferred over `[%eval]`,
+which is normalised when the PGN was annotated with score normalisation enabled.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct WdlData {
+    buckets: BTreeMap<i16, BTreeMap<i32, [u64; 3]>>,
+    results: [u64; 3],
+}
+
+impl WdlData {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<WdlData, WdlFitError> {
+        let path = path.as_ref();
+        let mut data = WdlData::default();
+
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("pgn"))
+        {
+            data.add_pgn(&fs::read_to_string(path)?)?;
+        } else {
+            data.add_packed(&fs::read(path)?)?;
+        }
+
+        Ok(data)
+    }
+
+    //0 = Loss, 1 = Draw, 2 = Win for the side to move
+    #[inline]
+    pub fn add(&mut self, material: i16, score: i32, result: u8) {
+        if score.abs() > WDL_FIT_MAX_SCORE || result > 2 {
+            return;
+        }
+
+        let material = material.clamp(WDL_MIN_MATERIAL, WDL_MAX_MATERIAL);
+        self.buckets
+            .entry(material)
+            .or_default()
+            .entry(score)
+            .or_default()[result as usize] += 1;
+        self.results[result as usize] += 1;
+    }
+
+    pub fn add_packed(&mut self, bytes: &[u8]) -> Result<(), WdlFitError> {
+        if !bytes.len().is_multiple_of(32) {
+            return Err(WdlFitError::InvalidSize(bytes.len()));
+        }
+
+        for chunk in bytes.chunks_exact(32) {
+            let board = PackedBoard::from_bytes(chunk.try_into().unwrap());
+            self.add(
+                board.classic_material(),
+                board.score() as i32,
+                board.result(),
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn add_pgn(&mut self, pgn: &str) -> Result<(), WdlFitError> {
+        for game in PgnReader::new(pgn) {
+            let game = game.map_err(|e| WdlFitError::InvalidPgn(e.to_string()))?;
+            let winner = match game.result {
+                GameResult::WhiteWin => Some(Color::White),
+                GameResult::BlackWin => Some(Color::Black),
+                GameResult::Draw => None,
+                GameResult::Unknown => continue,
+            };
+
+            let mut board = game.start.clone();
+            for pgn_move in &game.moves {
+                let before = board.clone();
+                board.make_move(pgn_move.mv);
+
+                let Some((score, white_after)) =
+                    pgn_move.comments.iter().find_map(|c| pgn_score(c))
+                else {
+                    continue;
+                };
+
+                let (pos, score) = if white_after {
+                    match board.stm() {
+                        Color::White => (&board, score),
+                        Color::Black => (&board, -score),
+                    }
+                } else {
+                    (&before, score)
+                };
+
+                if pos.in_check() {
+                    continue;
+                }
+
+                let result = match winner {
+                    Some(color) if color == pos.stm() => 2,
+                    Some(_) => 0,
+                    None => 1,
+                };
+
+                self.add(pos.classic_material(), score, result);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn positions(&self) -> u64 {
+        self.results.iter().sum()
+    }
+
+    //[loss, draw, win] counts for the side to move
+    #[inline]
+    pub fn results(&self) -> [u64; 3] {
+        self.results
+    }
+
+    fn groups(&self) -> Vec<WdlGroup> {
+        self.buckets
+            .iter()
+            .map(|(&material, scores)| {
+                let scores = scores
+                    .iter()
+                    .map(|(&score, counts)| (score as f64, counts.map(|count| count as f64)))
+                    .collect();
+
+                (material, scores)
+            })
+            .collect()
+    }
+
+    //Average negative log-likelihood of the results under the given model
+    pub fn loss(&self, model: &WdlModel) -> f64 {
+        model_nll(&self.groups(), model) / self.positions().max(1) as f64
+    }
+
+    //Average predicted [loss, draw, win] probabilities under the given model
+    pub fn predicted(&self, model: &WdlModel) -> [f64; 3] {
+        let mut predicted = [0.0; 3];
+        for (material, scores) in self.groups() {
+            for (score, counts) in scores {
+                let (w, l) = model.probabilities(score, material);
+                let total = counts.iter().sum::<f64>();
+
+                predicted[0] += total * l;
+                predicted[1] += total * (1.0 - w - l);
+                predicted[2] += total * w;
+            }
+        }
+
+        predicted.map(|p| p / self.positions().max(1) as f64)
+    }
+
+    /*
+    Maximum likelihood fit of both polynomials:
+    `a` and `b` are fitted separately for every material count with enough positions,
+    a weighted least squares fit of cubics through those gives the starting point,
+    which is then refined by maximizing the likelihood of all positions at once.
+    */
+    pub fn fit(&self, start: &WdlModel) -> Result<WdlModel, WdlFitError> {
+        let groups = self.groups();
+        let mut points = Vec::new();
+
+        for (material, scores) in &groups {
+            let total = scores
+                .iter()
+                .map(|(_, counts)| counts.iter().sum::<f64>())
+                .sum::<f64>();
+            if total < WDL_FIT_MIN_POSITIONS as f64 {
+                continue;
+            }
+
+            let (a, b) = start.params(*material);
+            let params = nelder_mead(
+                |x| group_nll(scores, x[0], x[1]),
+                &[a, b],
+                &[a.abs() * 0.1 + 10.0, b.abs() * 0.1 + 5.0],
+            );
+
+            points.push((WdlModel::material_scale(*material), params, total));
+        }
+
+        if points.len() < 4 {
+            return Err(WdlFitError::NotEnoughData(self.positions()));
+        }
+
+        let fitted = |i: usize| {
+            let coeffs = points
+                .iter()
+                .map(|(m, params, weight)| (*m, params[i], *weight))
+                .collect::<Vec<_>>();
+            fit_cubic(&coeffs)
+        };
+        let initial = WdlModel {
+            a: fitted(0),
+            b: fitted(1),
+        };
+
+        let start = if model_nll(&groups, &initial) <= model_nll(&groups, start) {
+            initial
+        } else {
+            *start
+        };
+        let coeffs = start.a.iter().chain(&start.b).copied().collect::<Vec<_>>();
+        let steps = coeffs
+            .iter()
+            .map(|c| c.abs() * 0.05 + 1.0)
+            .collect::<Vec<_>>();
+        let to_model = |x: &[f64]| WdlModel {
+            a: x[0..4].try_into().unwrap(),
+            b: x[4..8].try_into().unwrap(),
+        };
+
+        let coeffs = nelder_mead(|x| model_nll(&groups, &to_model(x)), &coeffs, &steps);
+        Ok(to_model(&coeffs))
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+/*
+Parses the score of a move comment, returning centipawns:
+- `[%cp_raw 35]` is the raw score in centipawns, White-relative and refers to the position after the move.
+- `[%eval 0.35]` is in pawns, White-relative and refers to the position after the move.
+- `+0.35/12 0.5s` (cutechess) is from the mover's perspective and refers to the position before the move.
+Mate scores are ignored.
+*/
+fn pgn_score(comment: &str) -> Option<(i32, bool)> {
+    let tag_value = |i: usize| {
+        comment[i..]
+            .split(|c: char| c == ']' || c == ',' || c.is_whitespace())
+            .next()
+    };
+
+    if let Some(i) = comment.find("[%cp_raw ") {
+        return Some((tag_value(i + 9)?.parse::<i32>().ok()?, true));
+    }
+
+    if let Some(i) = comment.find("[%eval ") {
+        let pawns = tag_value(i + 7)?.parse::<f64>().ok()?;
+
+        return Some((f64::round(pawns * 100.0) as i32, true));
+    }
+
+    let (value, _) = comment.split_whitespace().next()?.split_once('/')?;
+    let pawns = value.parse::<f64>().ok()?;
+
+    Some((f64::round(pawns * 100.0) as i32, false))
+}
+
+#[inline]
+fn group_nll(scores: &[(f64, [f64; 3])], a: f64, b: f64) -> f64 {
+    if b <= 0.0 {
+        return f64::INFINITY;
+    }
+
+    let mut nll = 0.0;
+    for &(score, counts) in scores {
+        let w = 1.0 / (1.0 + f64::exp((a - score) / b));
+        let l = 1.0 / (1.0 + f64::exp((a + score) / b));
+        let d = 1.0 - w - l;
+
+        nll -= counts[0] * l.max(WDL_FIT_EPSILON).ln()
+            + counts[1] * d.max(WDL_FIT_EPSILON).ln()
+            + counts[2] * w.max(WDL_FIT_EPSILON).ln();
+    }
+
+    nll
+}
+
+#[inline]
+fn model_nll(groups: &[WdlGroup], model: &WdlModel) -> f64 {
+    groups
+        .iter()
+        .map(|(material, scores)| {
+            let (a, b) = model.params(*material);
+            group_nll(scores, a, b)
+        })
+        .sum()
+}
+
+//Weighted least squares fit of `c[0] * x^3 + c[1] * x^2 + c[2] * x + c[3]`
+fn fit_cubic(points: &[(f64, f64, f64)]) -> [f64; 4] {
+    let mut matrix = [[0.0; 5]; 4];
+    for &(x, y, weight) in points {
+        let powers = [x * x * x, x * x, x, 1.0];
+        for i in 0..4 {
+            for j in 0..4 {
+                matrix[i][j] += weight * powers[i] * powers[j];
+            }
+            matrix[i][4] += weight * powers[i] * y;
+        }
+    }
+
+    //Gaussian elimination with partial pivoting
+    for col in 0..4 {
+        let pivot = (col..4)
+            .max_by(|&i, &j| matrix[i][col].abs().total_cmp(&matrix[j][col].abs()))
+            .unwrap();
+        matrix.swap(col, pivot);
+
+        for row in col + 1..4 {
+            let pivot_row = matrix[col];
+            let factor = matrix[row][col] / pivot_row[col];
+            for (value, &pivot) in matrix[row][col..].iter_mut().zip(&pivot_row[col..]) {
+                *value -= factor * pivot;
+            }
+        }
+    }
+
+    let mut coeffs = [0.0; 4];
+    for row in (0..4).rev() {
+        let sum = (row + 1..4)
+            .map(|k| matrix[row][k] * coeffs[k])
+            .sum::<f64>();
+        coeffs[row] = (matrix[row][4] - sum) / matrix[row][row];
+    }
+
+    coeffs
+}
+
+/*
+Derivative-free minimization, restarted a few times
+so that a collapsed simplex doesn't end the search early.
+*/
+fn nelder_mead(f: impl Fn(&[f64]) -> f64, start: &[f64], steps: &[f64]) -> Vec<f64> {
+    const RESTARTS: usize = 4;
+    const MAX_ITERS: usize = 2000;
+    const TOLERANCE: f64 = 1e-10;
+
+    let n = start.len();
+    let mut best = start.to_vec();
+
+    for restart in 0..RESTARTS {
+        let scale = 0.5f64.powi(restart as i32);
+        let mut simplex = (0..=n)
+            .map(|i| {
+                let mut x = best.clone();
+                if i > 0 {
+                    x[i - 1] += steps[i - 1] * scale;
+                }
+
+                let fx = f(&x);
+                (x, fx)
+            })
+            .collect::<Vec<_>>();
+
+        for _ in 0..MAX_ITERS {
+            simplex.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+            let (f_best, f_worst) = (simplex[0].1, simplex[n].1);
+            if f_worst - f_best <= TOLERANCE * f_best.abs().max(1.0) {
+                break;
+            }
+
+            let centroid = (0..n)
+                .map(|j| simplex[..n].iter().map(|(x, _)| x[j]).sum::<f64>() / n as f64)
+                .collect::<Vec<_>>();
+            let point = |t: f64| {
+                centroid
+                    .iter()
+                    .zip(&simplex[n].0)
+                    .map(|(&c, &w)| c + t * (c - w))
+                    .collect::<Vec<_>>()
+            };
+
+            let reflected = point(1.0);
+            let f_reflected = f(&reflected);
+
+            if f_reflected < f_best {
+                let expanded = point(2.0);
+                let f_expanded = f(&expanded);
+                simplex[n] = if f_expanded < f_reflected {
+                    (expanded, f_expanded)
+                } else {
+                    (reflected, f_reflected)
+                };
+            } else if f_reflected < simplex[n - 1].1 {
+                simplex[n] = (reflected, f_reflected);
+            } else {
+                let contracted = point(if f_reflected < f_worst { 0.5 } else { -0.5 });
+                let f_contracted = f(&contracted);
+
+                if f_contracted < f_reflected.min(f_worst) {
+                    simplex[n] = (contracted, f_contracted);
+                } else {
+                    let anchor = simplex[0].0.clone();
+                    for (x, fx) in &mut simplex[1..] {
+                        for (xi, &ai) in x.iter_mut().zip(&anchor) {
+                            *xi = ai + 0.5 * (*xi - ai);
+                        }
+
+                        *fx = f(x);
+                    }
+                }
+            }
+        }
+
+        let (x, _) = simplex
+            .into_iter()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .unwrap();
+        best = x;
+    }
+
+    best
+}
+
+/*----------------------------------------------------------------*/
+
+#[inline]
+fn format_coeffs(coeffs: &[f64; 4]) -> String {
+    format!(
+        "[{:.8}, {:.8}, {:.8}, {:.8}]",
+        coeffs[0], coeffs[1], coeffs[2], coeffs[3]
+    )
+}
+
+impl Engine {
+    pub fn fit_wdl(&mut self, path: &str, apply: bool) {
+        let data = match WdlData::load(path) {
+            Ok(data) => data,
+            Err(e) => {
+                self.message(e);
+                return;
+            }
+        };
+
+        let [losses, draws, wins] = data.results();
+        if !self.options.json {
+            println!(
+                "info string fitwdl positions {} W {wins} D {draws} L {losses}",
+                data.positions()
+            );
+        }
+
+        let old = current_wdl_model();
+        let new = match data.fit(&old) {
+            Ok(model) => model,
+            Err(e) => {
+                self.message(e);
+                return;
+            }
+        };
+
+        let total = data.positions() as f64;
+        let observed = [wins, draws, losses].map(|count| 100.0 * count as f64 / total);
+        let [p_loss, p_draw, p_win] = data.predicted(&new);
+        let predicted = [p_win, p_draw, p_loss].map(|p| 100.0 * p);
+        let (old_loss, new_loss) = (data.loss(&old), data.loss(&new));
+        let old_norm = old.params(WDL_REF_MATERIAL).0;
+        let new_norm = new.params(WDL_REF_MATERIAL).0;
+
+        if self.options.json {
+            let mut output = JsonObject::new("fitwdl");
+            output
+                .num("positions", data.positions())
+                .num("wins", wins)
+                .num("draws", draws)
+                .num("losses", losses)
+                .num_array("a", new.a.map(|c| format!("{c:.8}")))
+                .num_array("b", new.b.map(|c| format!("{c:.8}")))
+                .num("loss_old", format!("{old_loss:.6}"))
+                .num("loss_new", format!("{new_loss:.6}"))
+                .num_array("observed", observed.map(|p| format!("{p:.2}")))
+                .num_array("predicted", predicted.map(|p| format!("{p:.2}")))
+                .num("normalisation_old", format!("{old_norm:.0}"))
+                .num("normalisation_new", format!("{new_norm:.0}"));
+
+            println!("{output}");
+        } else {
+            println!("info string fitwdl a = {}", format_coeffs(&new.a));
+            println!("info string fitwdl b = {}", format_coeffs(&new.b));
+            println!("info string fitwdl loss old {old_loss:.6} new {new_loss:.6}");
+            println!(
+                "info string fitwdl observed W {:.2}% D {:.2}% L {:.2}% predicted W {:.2}% D {:.2}% L {:.2}%",
+                observed[0], observed[1], observed[2], predicted[0], predicted[1], predicted[2],
+            );
+            println!("info string fitwdl normalisation old {old_norm:.0} new {new_norm:.0}");
+        }
+
+        if apply {
+            set_wdl_model(new);
+            self.message("Applied the fitted WDL model");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pgn_scores() {
+        for (comment, score) in [
+            ("[%eval 0.35]", Some((35, true))),
+            ("[%eval -1.2] [%wdl 10/500/490]", Some((-120, true))),
+            ("[%eval 1.50] [%cp_raw 212]", Some((212, true))),
+            ("[%cp_raw -40] [%eval -0.30]", Some((-40, true))),
+            ("[%eval #3]", None),
+            ("+0.35/12 0.5s", Some((35, false))),
+            ("-1.07/20 1.2s", Some((-107, false))),
+            ("+M5/30 0.1s", None),
+            ("book", None),
+        ] {
+            assert_eq!(pgn_score(comment), score, "{comment}");
+        }
+    }
+
+    #[test]
+    fn cubic_fit() {
+        let coeffs = [2.0, -3.0, 0.5, 7.0];
+        let cubic = |x: f64| ((coeffs[0] * x + coeffs[1]) * x + coeffs[2]) * x + coeffs[3];
+        let points = (0..10)
+            .map(|i| {
+                let x = i as f64 / 4.0;
+                (x, cubic(x), 1.0 + i as f64)
+            })
+            .collect::<Vec<_>>();
+
+        for (fitted, expected) in fit_cubic(&points).into_iter().zip(coeffs) {
+            assert!((fitted - expected).abs() < 1e-6, "{fitted} != {expected}");
+        }
+    }
+}
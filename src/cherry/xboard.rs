@@ -0,0 +1,476 @@
+use std::{
+    num::{ParseFloatError, ParseIntError},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use crate::*;
+
+/*----------------------------------------------------------------*/
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Protocol {
+    Uci,
+    Xboard,
+}
+
+#[derive(Debug, Clone)]
+pub enum XboardCommand {
+    Xboard,
+    Protover(u32),
+    Accepted,
+    Rejected,
+    New,
+    Variant(String),
+    Force,
+    Go,
+    PlayOther,
+    UserMove(String),
+    MoveNow,
+    Level { moves: u16, base: u64, inc: u64 },
+    St(u64),
+    Sd(u8),
+    Time(u64),
+    Otim(u64),
+    Analyze,
+    Exit,
+    SetBoard(String),
+    Undo,
+    Remove,
+    Post,
+    NoPost,
+    Ping(String),
+    Memory(u64),
+    Cores(u32),
+    GameOver,
+    Ignored,
+    Quit,
+}
+
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum XboardParseError {
+    #[error("Missing Command")]
+    MissingCommand,
+    #[error("Unknown Command: `{0}`")]
+    UnknownCommand(String),
+    #[error("Missing argument for `{0}`")]
+    MissingArgument(String),
+    #[error("Invalid Time Control: `{0}`")]
+    InvalidTimeControl(String),
+    #[error("Error parsing integer: `{0}`")]
+    InvalidInteger(#[from] ParseIntError),
+    #[error("Error parsing number: `{0}`")]
+    InvalidNumber(#[from] ParseFloatError),
+}
+
+impl XboardCommand {
+    pub fn parse(input: &str) -> Result<XboardCommand, XboardParseError> {
+        use XboardCommand::*;
+        use XboardParseError::*;
+
+        let mut reader = input.split_ascii_whitespace();
+        let cmd = reader.next().ok_or(MissingCommand)?;
+        let mut arg = || reader.next().ok_or(MissingArgument(cmd.to_string()));
+
+        match cmd {
+            "xboard" => Ok(Xboard),
+            "protover" => Ok(Protover(arg()?.parse()?)),
+            "accepted" => Ok(Accepted),
+            "rejected" => Ok(Rejected),
+            "new" => Ok(New),
+            "variant" => Ok(Variant(arg()?.to_string())),
+            "force" => Ok(Force),
+            "go" => Ok(Go),
+            "playother" => Ok(PlayOther),
+            "usermove" => Ok(UserMove(arg()?.to_string())),
+            "?" => Ok(MoveNow),
+            "level" => {
+                let moves = arg()?.parse::<u16>()?;
+                let base = arg()?;
+                let inc = arg()?.parse::<f64>()?;
+
+                //base time is either `minutes` or `minutes:seconds`
+                let base = match base.split_once(':') {
+                    Some((min, sec)) => min.parse::<u64>()? * 60 + sec.parse::<u64>()?,
+                    None => (base.parse::<f64>()? * 60.0) as u64,
+                };
+
+                if inc < 0.0 {
+                    return Err(InvalidTimeControl(input.to_string()));
+                }
+
+                Ok(Level {
+                    moves,
+                    base: base * 1000,
+                    inc: (inc * 1000.0) as u64,
+                })
+            }
+            "st" => Ok(St((arg()?.parse::<f64>()? * 1000.0) as u64)),
+            "sd" => Ok(Sd(arg()?.parse()?)),
+            "time" => Ok(Time(arg()?.parse::<i64>()?.max(0) as u64 * 10)),
+            "otim" => Ok(Otim(arg()?.parse::<i64>()?.max(0) as u64 * 10)),
+            "analyze" => Ok(Analyze),
+            "exit" => Ok(Exit),
+            "setboard" => Ok(SetBoard(
+                reader.remainder().unwrap_or("").trim().to_string(),
+            )),
+            "undo" => Ok(Undo),
+            "remove" => Ok(Remove),
+            "post" => Ok(Post),
+            "nopost" => Ok(NoPost),
+            "ping" => Ok(Ping(arg()?.to_string())),
+            "memory" => Ok(Memory(arg()?.parse()?)),
+            "cores" => Ok(Cores(arg()?.parse()?)),
+            "result" => Ok(GameOver),
+            "random" | "hard" | "easy" | "computer" | "name" | "rating" | "ics" | "." | "draw"
+            | "hint" | "bk" | "white" | "black" => Ok(Ignored),
+            "quit" => Ok(Quit),
+            _ => {
+                //older GUIs send moves without the `usermove` prefix
+                if (cmd.len() >= 4 && cmd.as_bytes()[1].is_ascii_digit()) || cmd.starts_with("O-O")
+                {
+                    Ok(UserMove(cmd.to_string()))
+                } else {
+                    Err(UnknownCommand(cmd.to_string()))
+                }
+            }
+        }
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+pub struct XboardState {
+    pub start: Board,
+    pub moves: Vec<Move>,
+    pub engine_color: Option<Color>,
+    pub analyze: bool,
+    pub post: bool,
+    pub thinking: bool,
+    pub report_move: Arc<AtomicBool>,
+
+    pub moves_per_session: u16,
+    pub base_time: u64,
+    pub inc: u64,
+    pub move_time: Option<u64>,
+    pub depth: Option<u8>,
+    pub time: u64,
+    pub otim: u64,
+}
+
+impl Default for XboardState {
+    #[inline]
+    fn default() -> Self {
+        XboardState {
+            start: Board::startpos(),
+            moves: Vec::new(),
+            engine_color: Some(Color::Black),
+            analyze: false,
+            post: false,
+            thinking: false,
+            report_move: Arc::new(AtomicBool::new(false)),
+
+            moves_per_session: 0,
+            base_time: 0,
+            inc: 0,
+            move_time: None,
+            depth: None,
+            time: 0,
+            otim: 0,
+        }
+    }
+}
+
+#[inline]
+pub fn xboard_move(board: &Board, mv: Move, frc: bool) -> String {
+    match mv.flag() {
+        MoveFlag::ShortCastling if frc => String::from("O-O"),
+        MoveFlag::LongCastling if frc => String::from("O-O-O"),
+        _ => mv.display(board, frc).to_string(),
+    }
+}
+
+#[inline]
+pub fn xboard_score(score: Score, board: &Board, normalisation: bool) -> i32 {
+    match score.mate_in() {
+        Some(ply) => ply.signum() as i32 * (100000 + (ply.abs() as i32 + 1) / 2),
+        None if normalisation => score.normalise(board.classic_material()).0,
+        None => score.0,
+    }
+}
+
+/*----------------------------------------------------------------*/
+
+impl Engine {
+    pub fn handle_xboard(&mut self, input: &str) -> Abort {
+        self.xboard_sync();
+
+        let cmd = match XboardCommand::parse(input) {
+            Ok(cmd) => cmd,
+            Err(XboardParseError::UnknownCommand(cmd)) => {
+                println!("Error (unknown command): {cmd}");
+                return Abort::No;
+            }
+            Err(e) => {
+                println!("Error ({e}): {input}");
+                return Abort::No;
+            }
+        };
+
+        match cmd {
+            XboardCommand::Xboard => println!(),
+            XboardCommand::Protover(_) => self.xboard_features(),
+            XboardCommand::Accepted | XboardCommand::Rejected | XboardCommand::Ignored => {}
+            XboardCommand::New => {
+                self.xboard_cancel();
+                self.options.frc = false;
+                self.xboard.engine_color = Some(Color::Black);
+                self.xboard.move_time = None;
+                self.xboard.depth = None;
+                self.xboard_set_board(Board::startpos());
+                self.searcher.newgame();
+            }
+            XboardCommand::Variant(variant) => match variant.as_str() {
+                "normal" => self.options.frc = false,
+                "fischerandom" => self.options.frc = true,
+                _ => println!("Error (unsupported variant): {variant}"),
+            },
+            XboardCommand::Force => {
+                self.xboard_cancel();
+                self.xboard.engine_color = None;
+            }
+            XboardCommand::Go => {
+                self.xboard_cancel();
+                self.xboard.engine_color = Some(self.pos.stm());
+                self.xboard_think();
+            }
+            XboardCommand::PlayOther => {
+                self.xboard_cancel();
+                self.xboard.engine_color = Some(!self.pos.stm());
+            }
+            XboardCommand::UserMove(mv) => {
+                let board = self.pos.board().clone();
+                let mv = Move::parse_san(&board, &mv)
+                    .or_else(|| Move::parse(&board, &mv))
+                    .filter(|mv| board.gen_moves().contains(mv));
+
+                let Some(mv) = mv else {
+                    println!(
+                        "Illegal move: {}",
+                        input.trim_start_matches("usermove").trim()
+                    );
+                    return Abort::No;
+                };
+
+                self.xboard_cancel();
+                self.xboard_make_move(mv);
+                self.xboard_think();
+            }
+            XboardCommand::MoveNow =>
+                if self.xboard.thinking && self.searcher.is_searching() {
+                    self.searcher.stop();
+                },
+            XboardCommand::Level { moves, base, inc } => {
+                self.xboard.moves_per_session = moves;
+                self.xboard.base_time = base;
+                self.xboard.inc = inc;
+                self.xboard.move_time = None;
+                self.xboard.time = base;
+                self.xboard.otim = base;
+            }
+            XboardCommand::St(time) => self.xboard.move_time = Some(time),
+            XboardCommand::Sd(depth) => self.xboard.depth = Some(depth),
+            XboardCommand::Time(time) => self.xboard.time = time,
+            XboardCommand::Otim(time) => self.xboard.otim = time,
+            XboardCommand::Analyze => {
+                self.xboard_cancel();
+                self.xboard.analyze = true;
+                self.xboard.engine_color = None;
+                self.xboard_think();
+            }
+            XboardCommand::Exit => {
+                self.xboard_cancel();
+                self.xboard.analyze = false;
+            }
+            XboardCommand::SetBoard(fen) => {
+                let board = match Board::from_fen(&fen) {
+                    Ok(board) => board,
+                    Err(e) => {
+                        println!("tellusererror Illegal position: {e}");
+                        return Abort::No;
+                    }
+                };
+
+                self.xboard_cancel();
+                self.xboard_set_board(board);
+                if self.xboard.analyze {
+                    self.xboard_think();
+                }
+            }
+            XboardCommand::Undo | XboardCommand::Remove => {
+                let plies = if matches!(cmd, XboardCommand::Undo) {
+                    1
+                } else {
+                    2
+                };
+                if self.xboard.moves.len() < plies {
+                    println!("Error (no moves to undo): {input}");
+                    return Abort::No;
+                }
+
+                self.xboard_cancel();
+                let moves = self.xboard.moves.len() - plies;
+                self.xboard.moves.truncate(moves);
+                self.xboard_replay();
+                if self.xboard.analyze {
+                    self.xboard_think();
+                }
+            }
+            XboardCommand::Post => self.xboard.post = true,
+            XboardCommand::NoPost => self.xboard.post = false,
+            XboardCommand::Ping(n) => println!("pong {n}"),
+            XboardCommand::Memory(mb) => {
+                self.xboard_cancel();
+                self.searcher.resize_ttable(mb.clamp(1, MAX_TT_SIZE));
+            }
+            XboardCommand::Cores(threads) => {
+                self.xboard_cancel();
+                self.searcher.set_threads(threads.clamp(1, MAX_THREADS));
+            }
+            XboardCommand::GameOver => {
+                self.xboard_cancel();
+                self.xboard.engine_color = None;
+            }
+            XboardCommand::Quit => {
+                self.xboard_cancel();
+                return Abort::Yes;
+            }
+        }
+
+        Abort::No
+    }
+
+    #[inline]
+    fn xboard_features(&self) {
+        println!("feature done=0");
+        println!("feature myname=\"Cherry v{ENGINE_VERSION}\"");
+        println!("feature setboard=1 usermove=1 ping=1 playother=1 analyze=1 colors=0");
+        println!("feature sigint=0 sigterm=0 reuse=1 time=1 draw=0 memory=1 smp=1");
+        println!("feature variants=\"normal,fischerandom\"");
+        println!("feature done=1");
+    }
+
+    /*----------------------------------------------------------------*/
+
+    //applies the move of a finished search to our own position
+    fn xboard_sync(&mut self) {
+        if !self.xboard.thinking || self.searcher.is_searching() {
+            return;
+        }
+
+        self.xboard.thinking = false;
+        if self.xboard.report_move.load(Ordering::Relaxed)
+            && let Some(mv) = self.searcher.shared.best_move()
+        {
+            self.xboard_make_move(mv);
+        }
+    }
+
+    //stops the current search without playing its move
+    fn xboard_cancel(&mut self) {
+        self.xboard.report_move.store(false, Ordering::Relaxed);
+
+        if self.searcher.is_searching() {
+            self.searcher.stop();
+        }
+
+        self.searcher.wait();
+        self.xboard.thinking = false;
+    }
+
+    fn xboard_think(&mut self) {
+        if self.pos.board().status() != BoardStatus::Ongoing {
+            return;
+        }
+
+        let stm = self.pos.stm();
+        let mut limits = Vec::new();
+
+        if self.xboard.analyze {
+            self.xboard.report_move.store(false, Ordering::Relaxed);
+        } else if self.xboard.engine_color == Some(stm) {
+            self.xboard.report_move.store(true, Ordering::Relaxed);
+
+            if let Some(time) = self.xboard.move_time {
+                limits.push(SearchLimit::MoveTime(time));
+            } else if self.xboard.base_time > 0 || self.xboard.time > 0 {
+                let (white, black) = match stm {
+                    Color::White => (self.xboard.time, self.xboard.otim),
+                    Color::Black => (self.xboard.otim, self.xboard.time),
+                };
+
+                limits.push(SearchLimit::WhiteTime(white));
+                limits.push(SearchLimit::BlackTime(black));
+                limits.push(SearchLimit::WhiteInc(self.xboard.inc));
+                limits.push(SearchLimit::BlackInc(self.xboard.inc));
+
+                let mps = self.xboard.moves_per_session;
+                if mps > 0 {
+                    let played = (self.pos.board().fullmove_count() - 1) % mps;
+                    limits.push(SearchLimit::MovesToGo(mps - played));
+                }
+            }
+
+            if let Some(depth) = self.xboard.depth {
+                limits.push(SearchLimit::Depth(depth));
+            }
+        } else {
+            return;
+        }
+
+        //without any time control, don't think forever
+        if !self.xboard.analyze && limits.is_empty() {
+            limits.push(SearchLimit::MoveTime(5000));
+        }
+
+        self.xboard.thinking = true;
+        self.searcher.search(
+            self.pos.clone(),
+            limits,
+            self.options,
+            SearchInfo::new(XboardReporter {
+                post: self.xboard.post || self.xboard.analyze,
+                normalisation: self.options.score_normalisation,
+                frc: self.options.frc,
+                report_move: self.xboard.report_move.clone(),
+            }),
+        );
+    }
+
+    /*----------------------------------------------------------------*/
+
+    #[inline]
+    fn xboard_make_move(&mut self, mv: Move) {
+        self.xboard.moves.push(mv);
+        self.pos.make_move(mv);
+        self.pos.reset_nnue();
+    }
+
+    #[inline]
+    fn xboard_set_board(&mut self, board: Board) {
+        self.xboard.start = board;
+        self.xboard.moves.clear();
+        self.xboard_replay();
+    }
+
+    #[inline]
+    fn xboard_replay(&mut self) {
+        self.pos.set_board(self.xboard.start.clone());
+        for &mv in &self.xboard.moves {
+            self.pos.make_move(mv);
+            self.pos.reset_nnue();
+        }
+    }
+}
@@ -0,0 +1,10 @@
+#![feature(str_split_whitespace_remainder)]
+#![feature(stmt_expr_attributes)]
+#![feature(generic_const_exprs)]
+#![feature(sync_unsafe_cell)]
+#![allow(incomplete_features)]
+#![allow(dead_code)]
+
+mod cherry;
+
+pub use cherry::*;
@@ -1,13 +1,4 @@
-#![feature(str_split_whitespace_remainder)]
-#![feature(stmt_expr_attributes)]
-#![feature(generic_const_exprs)]
-#![feature(sync_unsafe_cell)]
-#![allow(incomplete_features)]
-#![allow(dead_code)]
-
-mod cherry;
-
-use std::{env, io, sync::LazyLock};
+use std::{env, io, process, sync::LazyLock};
 
 use cherry::*;
 use colored::Colorize;
@@ -19,7 +10,26 @@ fn main() {
 
     let mut buffer = String::new();
     let mut engine = Engine::new();
-    let args = env::args().skip(1).collect::<Vec<String>>();
+    let mut args = env::args().skip(1).collect::<Vec<String>>();
+
+    if let Some(i) = args.iter().position(|arg| arg == "--json") {
+        args.remove(i);
+        engine.options.json = true;
+    }
+
+    if let Some(i) = args.iter().position(|arg| arg == "--weights") {
+        args.remove(i);
+        if i >= args.len() {
+            engine.message("Missing file path for `--weights`");
+            process::exit(1);
+        }
+
+        let path = args.remove(i);
+        if let Err(e) = W::load(&path) {
+            engine.message(format!("Failed to load Weights from `{path}`: {e}"));
+            process::exit(1);
+        }
+    }
 
     if !args.is_empty() {
         for cmd in args {
@@ -31,7 +41,10 @@ fn main() {
         return;
     }
 
-    println!("Cherry v{} by Tecci", ENGINE_VERSION.bright_green());
+    if !engine.options.json {
+        println!("Cherry v{} by Tecci", ENGINE_VERSION.bright_green());
+    }
+
     while let Ok(_) = io::stdin().read_line(&mut buffer) {
         if buffer.trim().is_empty() {
             continue;